@@ -1,22 +1,38 @@
 use std::net::SocketAddr;
-use std::str::FromStr;
-
-use futures::StreamExt;
-use tokio::sync::mpsc;
-
-use warp::ws::WebSocket;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use libdump1090_rs::mode_s::MessageValidator;
+use libdump1090_rs::net_io::avr::{encode_avr, encode_avr_mlat};
+use libdump1090_rs::net_io::beast::encode_beast;
+use libdump1090_rs::tracker::{Tracker, TrackerConfig};
+use libdump1090_rs::ModesMessage;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use warp::ws::{Message, WebSocket};
 use warp::*;
 
+mod net;
+mod net_input;
+mod options;
+mod pipeline;
+mod record;
+mod replay;
+mod sdrconfig;
+mod source;
 mod webserver;
-use webserver::read_loop::read_loop;
-
-use tiny_tokio_actor::*;
-
-#[derive(Clone, Debug)]
-struct ServerEvent();
+use net::OutputServer;
+use net_input::{run_connector, InputFormat, InputServer};
+use options::Config;
+use pipeline::Pipeline;
+use replay::LogReplay;
+use source::SampleReader;
+use webserver::aircraft_json::AircraftJson;
 
-// Mark the struct as a system event message.
-impl SystemEvent for ServerEvent {}
+// Messages buffered per subscriber before a slow client starts missing them
+const MESSAGE_CHANNEL_CAPACITY: usize = 1024;
 
 #[tokio::main]
 async fn main() {
@@ -25,24 +41,60 @@ async fn main() {
     dotenv::from_path(path).ok();
     env_logger::init();
 
-    // set port to 127.0.0.1 and port to 9000
-    let addr = std::env::var("HOST_PORT")
-        .ok()
-        .and_then(|string| SocketAddr::from_str(&string).ok())
-        .unwrap_or_else(|| SocketAddr::from_str("127.0.0.1:9000").unwrap());
+    // parse and validate everything before touching the sdr or the network
+    let config = Config::load().unwrap_or_else(|e| exit_with_error(&e));
+    let log_replay = LogReplay::open(&config.options).unwrap_or_else(|e| exit_with_error(&e));
+    let reader = if config.options.net_only || log_replay.is_some() {
+        None
+    } else {
+        Some(SampleReader::open(&config).unwrap_or_else(|e| exit_with_error(&e)))
+    };
+
+    // aircraft state shared by every connection
+    let tracker = Arc::new(Mutex::new(Tracker::new(TrackerConfig {
+        receiver: config.options.receiver(),
+        max_range: config.options.max_range,
+        ..TrackerConfig::default()
+    })));
+
+    // forget aircraft that are no longer heard
+    let expire_tracker = tracker.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            expire_tracker.lock().unwrap().expire();
+        }
+    });
 
-    // Create the event bus and actor system
-    let bus = EventBus::<ServerEvent>::new(1000);
-    let system = ActorSystem::new("echo", bus);
+    // decoded messages are fanned out to every connected client
+    let (messages, _) = broadcast::channel::<ModesMessage>(MESSAGE_CHANNEL_CAPACITY);
+    let (sbs_lines, _) = broadcast::channel::<String>(MESSAGE_CHANNEL_CAPACITY);
+    let pipeline = Pipeline::new(
+        tracker.clone(),
+        messages.clone(),
+        sbs_lines.clone(),
+        config.options.receiver(),
+        MessageValidator::new(usize::from(config.options.fix)),
+    );
 
     // Create the warp WebSocket route
+    let ws_messages = messages.clone();
     let ws = warp::path!("echo")
-        .and(warp::any().map(move || system.clone()))
+        .and(warp::any().map(move || ws_messages.subscribe()))
         .and(warp::addr::remote())
         .and(warp::ws())
-        .map(|system: ActorSystem<ServerEvent>, remote: Option<SocketAddr>, ws: warp::ws::Ws| {
-            ws.on_upgrade(move |websocket| start_echo(system, remote, websocket))
-        });
+        .map(
+            |messages: broadcast::Receiver<ModesMessage>,
+             remote: Option<SocketAddr>,
+             ws: warp::ws::Ws| {
+                ws.on_upgrade(move |websocket| start_echo(messages, remote, websocket))
+            },
+        );
+
+    // Route to serve the tracked aircraft
+    let aircraft_route = warp::path("aircraft.json")
+        .map(move || warp::reply::json(&AircraftJson::new(&tracker.lock().unwrap())));
 
     // Route to serve index.html
     let index_route =
@@ -59,56 +111,111 @@ async fn main() {
     });
 
     // Combine all routes
-    let routes = index_route.or(indexjs_route).or(adsbjs_route).or(css_route).or(ws);
+    let routes =
+        index_route.or(indexjs_route).or(adsbjs_route).or(css_route).or(aircraft_route).or(ws);
+
+    // Bind now so that a bad --host/--port fails before the sdr starts streaming
+    let (addr, server) = warp::serve(routes)
+        .try_bind_ephemeral(config.addr)
+        .unwrap_or_else(|e| exit_with_error(&format!("could not bind {}: {e}", config.addr)));
+    println!("[-] Serving on http://{addr}");
+
+    // tcp outputs for other tools
+    if let Some(addr) = config.net_addr(config.options.net_bo_port) {
+        let beast = OutputServer::bind("beast", addr, encode_beast)
+            .await
+            .unwrap_or_else(|e| exit_with_error(&e));
+        tokio::spawn(beast.run(messages.clone()));
+    }
+    if let Some(addr) = config.net_addr(config.options.net_ro_port) {
+        let encode = if config.options.net_ro_mlat { encode_avr_mlat } else { encode_avr };
+        let avr = OutputServer::bind("avr", addr, move |msg| encode(msg).into_bytes())
+            .await
+            .unwrap_or_else(|e| exit_with_error(&e));
+        tokio::spawn(avr.run(messages.clone()));
+    }
+    if let Some(addr) = config.net_addr(config.options.net_sbs_port) {
+        // lines are formatted by the pipeline, while the tracker holds the state they describe
+        let sbs = OutputServer::bind("sbs", addr, |line: &String| line.as_bytes().to_vec())
+            .await
+            .unwrap_or_else(|e| exit_with_error(&e));
+        tokio::spawn(sbs.run(sbs_lines));
+    }
+
+    // messages from other receivers go through the same pipeline as the sdr
+    for connector in &config.options.net_connector {
+        tokio::spawn(run_connector(connector.clone(), pipeline.clone()));
+    }
+    let inputs = [
+        (config.options.net_bi_port, InputFormat::Beast),
+        (config.options.net_ri_port, InputFormat::Avr),
+    ];
+    for (port, format) in inputs {
+        if let Some(addr) = config.net_addr(port) {
+            let input =
+                InputServer::bind(addr, format).await.unwrap_or_else(|e| exit_with_error(&e));
+            tokio::spawn(input.run(pipeline.clone()));
+        }
+    }
+
+    // A single sample reader or replay for the lifetime of the process
+    if let Some(reader) = reader {
+        spawn_reader(move || reader.run(&pipeline));
+    } else if let Some(log_replay) = log_replay {
+        spawn_reader(move || log_replay.run(&pipeline));
+    }
 
-    // Start the server and await it
-    warp::serve(routes).run(addr).await;
+    server.await;
 }
 
-// Starts a new echo actor on our actor system.
-// This starts a new thread that reads in a loop
-// from the stream and sends the data to the ws_out
-async fn start_echo(
-    _system: ActorSystem<ServerEvent>,
-    _remote: Option<SocketAddr>,
-    websocket: WebSocket,
-) {
-    // Split out the websocket into incoming and outgoing
-    let (ws_out, _ws_in) = websocket.split();
+// Run a blocking source of messages on its own thread
+fn spawn_reader<F>(run: F)
+where
+    F: FnOnce() -> Result<(), String> + Send + 'static,
+{
+    let reader = tokio::task::spawn_blocking(run);
     tokio::spawn(async move {
-        read_loop(ws_out).await;
+        match reader.await {
+            // a file, pipe or log ran out, keep serving what was decoded
+            Ok(Ok(())) => println!("[-] End of input"),
+            // exit with error code as 1 so that systemctl can restart
+            Ok(Err(e)) => exit_with_error(&e),
+            Err(_) => exit_with_error("sample reader stopped"),
+        }
     });
 }
 
-#[derive(Clone)]
-struct EchoActor {
-    sender: mpsc::UnboundedSender<warp::ws::Message>,
+fn exit_with_error(e: &str) -> ! {
+    println!("[!] Exiting: {e}");
+    std::process::exit(1);
 }
 
-impl EchoActor {
-    pub fn new(sender: mpsc::UnboundedSender<warp::ws::Message>) -> Self {
-        EchoActor { sender }
+// Forward decoded messages as hex to a websocket client until it disconnects.
+// The sdr keeps running no matter how many clients come and go.
+async fn start_echo(
+    mut messages: broadcast::Receiver<ModesMessage>,
+    remote: Option<SocketAddr>,
+    websocket: WebSocket,
+) {
+    // Split out the websocket into incoming and outgoing
+    let (mut ws_out, _ws_in) = websocket.split();
+    ::log::debug!("websocket client connected: {remote:?}");
+
+    loop {
+        let msg = match messages.recv().await {
+            Ok(msg) => msg,
+            Err(RecvError::Lagged(skipped)) => {
+                ::log::warn!("websocket client {remote:?} lagging, skipped {skipped} messages");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        // send on ws_out the hex data
+        if ws_out.send(Message::text(hex::encode(&msg))).await.is_err() {
+            break;
+        }
     }
-}
-
-impl Actor<ServerEvent> for EchoActor {}
 
-#[derive(Clone, Debug)]
-struct EchoRequest(warp::ws::Message);
-
-impl Message for EchoRequest {
-    type Response = ();
-}
-
-#[async_trait]
-impl Handler<ServerEvent, EchoRequest> for EchoActor {
-    async fn handle(&mut self, msg: EchoRequest, ctx: &mut ActorContext<ServerEvent>) {
-        ::log::debug!(
-            "actor {} on system {} received message! {:?}",
-            &ctx.path,
-            ctx.system.name(),
-            &msg
-        );
-        self.sender.send(msg.0).unwrap()
-    }
+    ::log::debug!("websocket client disconnected: {remote:?}");
 }
@@ -0,0 +1,77 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Turns a broadcast item into the bytes sent to clients, nothing is sent for an empty result
+pub type Formatter<T> = Arc<dyn Fn(&T) -> Vec<u8> + Send + Sync>;
+
+/// A tcp listener that streams every decoded message to all connected clients in one format,
+/// like the dump1090 `--net-*-port` outputs
+///
+/// Items are usually messages, or lines that were already formatted when the message was merged
+/// into the tracker.
+pub struct OutputServer<T> {
+    name: &'static str,
+    listener: TcpListener,
+    format: Formatter<T>,
+}
+
+impl<T: Clone + Send + 'static> OutputServer<T> {
+    /// Bind to `addr` now, so that a port already in use is reported at startup
+    pub async fn bind(
+        name: &'static str,
+        addr: SocketAddr,
+        format: impl Fn(&T) -> Vec<u8> + Send + Sync + 'static,
+    ) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("could not bind {name} output to {addr}: {e}"))?;
+        println!("[-] Serving {name} output on {addr}");
+        Ok(Self { name, listener, format: Arc::new(format) })
+    }
+
+    /// Accept clients forever, each one gets its own subscription to `messages`
+    pub async fn run(self, messages: broadcast::Sender<T>) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, remote)) => {
+                    ::log::info!("{} client connected: {remote}", self.name);
+                    let client = serve_client(stream, messages.subscribe(), self.format.clone());
+                    let name = self.name;
+                    tokio::spawn(async move {
+                        client.await;
+                        ::log::info!("{name} client disconnected: {remote}");
+                    });
+                }
+                Err(e) => ::log::warn!("{} accept failed: {e}", self.name),
+            }
+        }
+    }
+}
+
+// Write formatted messages until the client goes away
+async fn serve_client<T: Clone>(
+    mut stream: TcpStream,
+    mut messages: broadcast::Receiver<T>,
+    format: Formatter<T>,
+) {
+    loop {
+        let msg = match messages.recv().await {
+            Ok(msg) => msg,
+            Err(RecvError::Lagged(skipped)) => {
+                ::log::warn!("tcp client lagging, skipped {skipped} messages");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let out = format(&msg);
+        if !out.is_empty() && stream.write_all(&out).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use libdump1090_rs::net_io::avr::decode_avr;
+use libdump1090_rs::net_io::beast::BeastDecoder;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::pipeline::Pipeline;
+
+// Wait between attempts to reach a `--net-connector`, like dump1090
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Wire format of a network input
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Mode-S Beast binary frames, as on port 30005
+    Beast,
+    /// AVR lines, `*<hex>;` or `@<timestamp><hex>;`, as on port 30002
+    Avr,
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Beast => write!(f, "beast"),
+            Self::Avr => write!(f, "avr"),
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "beast" => Ok(Self::Beast),
+            "avr" => Ok(Self::Avr),
+            _ => Err(format!("unknown input format {s:?}, expected beast or avr")),
+        }
+    }
+}
+
+/// A remote receiver to connect out to, `<host>:<port>,<format>` on the command line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Connector {
+    pub addr: String,
+    pub format: InputFormat,
+}
+
+impl FromStr for Connector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, format) = s
+            .rsplit_once(',')
+            .ok_or_else(|| format!("expected <host>:<port>,<format>, got {s:?}"))?;
+        if !addr.contains(':') {
+            return Err(format!("missing port in {addr:?}"));
+        }
+        Ok(Self { addr: addr.to_string(), format: format.parse()? })
+    }
+}
+
+/// Read from `connector` forever, reconnecting whenever the connection fails or is closed
+pub async fn run_connector(connector: Connector, pipeline: Pipeline) {
+    let Connector { addr, format } = connector;
+    loop {
+        match TcpStream::connect(&addr).await {
+            Ok(stream) => {
+                println!("[-] Connected to {format} input {addr}");
+                read_input(stream, format, &pipeline).await;
+                ::log::warn!("{format} input {addr} disconnected");
+            }
+            Err(e) => ::log::warn!("could not connect to {format} input {addr}: {e}"),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// A tcp listener that other receivers push messages to, like the dump1090 `--net-*i-port`
+/// inputs
+pub struct InputServer {
+    format: InputFormat,
+    listener: TcpListener,
+}
+
+impl InputServer {
+    /// Bind to `addr` now, so that a port already in use is reported at startup
+    pub async fn bind(addr: SocketAddr, format: InputFormat) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("could not bind {format} input to {addr}: {e}"))?;
+        println!("[-] Accepting {format} input on {addr}");
+        Ok(Self { format, listener })
+    }
+
+    /// Accept senders forever, reading each one until it disconnects
+    pub async fn run(self, pipeline: Pipeline) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, remote)) => {
+                    ::log::info!("{} input connected: {remote}", self.format);
+                    let (format, pipeline) = (self.format, pipeline.clone());
+                    tokio::spawn(async move {
+                        read_input(stream, format, &pipeline).await;
+                        ::log::info!("{format} input disconnected: {remote}");
+                    });
+                }
+                Err(e) => ::log::warn!("{} input accept failed: {e}", self.format),
+            }
+        }
+    }
+}
+
+// Decode messages from `reader` into the pipeline until it closes or fails. They are checked
+// like demodulated ones, a corrupted frame could otherwise add an aircraft that isn't there.
+async fn read_input<R: AsyncRead + Unpin>(mut reader: R, format: InputFormat, pipeline: &Pipeline) {
+    match format {
+        InputFormat::Beast => {
+            let mut decoder = BeastDecoder::new();
+            let mut buf = vec![0; 4096];
+            while let Ok(len @ 1..) = reader.read(&mut buf).await {
+                for msg in decoder.decode(&buf[..len]) {
+                    pipeline.publish_remote(msg);
+                }
+            }
+        }
+        InputFormat::Avr => {
+            // raw bytes, so that a line that isn't utf-8 is skipped instead of ending the input
+            let mut reader = BufReader::new(reader);
+            let mut line = vec![];
+            while let Ok(1..) = reader.read_until(b'\n', &mut line).await {
+                match std::str::from_utf8(&line) {
+                    Ok(text) => match decode_avr(text.trim_end()) {
+                        Ok(msg) => pipeline.publish_remote(msg),
+                        Err(e) => ::log::debug!("ignoring avr line {text:?}: {e}"),
+                    },
+                    Err(_) => ::log::warn!("ignoring avr line that isn't utf-8: {line:?}"),
+                }
+                line.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use libdump1090_rs::mode_s::MessageValidator;
+    use libdump1090_rs::net_io::beast::encode_beast;
+    use libdump1090_rs::synth::{encode_ac13_field, encode_df4};
+    use libdump1090_rs::tracker::Tracker;
+    use libdump1090_rs::ModesMessage;
+    use tokio::sync::broadcast;
+
+    use super::*;
+
+    #[test]
+    fn connector_parsing() {
+        assert_eq!(
+            "192.168.1.20:30005,beast".parse(),
+            Ok(Connector { addr: "192.168.1.20:30005".to_string(), format: InputFormat::Beast })
+        );
+        assert_eq!(
+            "rooftop.local:30002,avr".parse(),
+            Ok(Connector { addr: "rooftop.local:30002".to_string(), format: InputFormat::Avr })
+        );
+        assert!("192.168.1.20:30005".parse::<Connector>().is_err());
+        assert!("192.168.1.20,beast".parse::<Connector>().is_err());
+        assert!("192.168.1.20:30005,sbs".parse::<Connector>().is_err());
+    }
+
+    #[tokio::test]
+    async fn avr_input_skips_bad_lines() {
+        let tracker = Arc::new(Mutex::new(Tracker::default()));
+        let (messages, mut received) = broadcast::channel(16);
+        let (sbs, _) = broadcast::channel(16);
+        let pipeline = Pipeline::new(tracker, messages, sbs, None, MessageValidator::default());
+
+        let input = b"*8D4840D6202CC371C32CE0576098;\n\
+                      *8D\xff\xfe;\n\
+                      not hex\r\n\
+                      *5DAD92936265F5;\r\n";
+        read_input(&input[..], InputFormat::Avr, &pipeline).await;
+
+        assert_eq!(
+            received.try_recv().unwrap().bytes(),
+            hex::decode("8d4840d6202cc371c32ce0576098").unwrap()
+        );
+        assert_eq!(received.try_recv().unwrap().bytes(), hex::decode("5dad92936265f5").unwrap());
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn remote_messages_are_checked() {
+        let tracker = Arc::new(Mutex::new(Tracker::default()));
+        let (messages, _) = broadcast::channel(16);
+        let (sbs, _) = broadcast::channel(16);
+        let pipeline =
+            Pipeline::new(tracker.clone(), messages, sbs, None, MessageValidator::default());
+
+        let frame =
+            |bytes: &[u8]| encode_beast(&ModesMessage::from_bytes(bytes, 0, -10.0).unwrap());
+        let df17 = hex::decode("8d4840d6202cc371c32ce0576098").unwrap();
+        let mut corrupted = df17.clone();
+        corrupted[5] ^= 0x03;
+        let df4 = |address| encode_df4(address, 0, encode_ac13_field(35000).unwrap());
+        let input = [
+            // two bit errors are more than the default fix corrects
+            frame(&corrupted),
+            // no verified message from this address yet
+            frame(&df4(0x4840d6)),
+            frame(&df17),
+            frame(&df4(0x4840d6)),
+            frame(&df4(0x485020)),
+        ]
+        .concat();
+        read_input(&input[..], InputFormat::Beast, &pipeline).await;
+
+        let tracker = tracker.lock().unwrap();
+        assert_eq!(tracker.len(), 1);
+        let aircraft = tracker.get(0x4840d6).unwrap();
+        assert_eq!(aircraft.messages, 2);
+        assert_eq!(aircraft.callsign.as_ref().unwrap().value, "KLM1023");
+        assert!(aircraft.altitude.is_some());
+    }
+}
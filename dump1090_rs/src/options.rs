@@ -0,0 +1,359 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use libdump1090_rs::convert::SampleFormat;
+
+use crate::net_input::{Connector, InputFormat};
+use crate::replay::ReplaySpeed;
+use crate::sdrconfig::{Sdr, SdrConfig, DEFAULT_CONFIG};
+
+const CUSTOM_CONFIG_HELP: &str =
+    "Filepath for config.toml file overriding or adding sdr config values for soapysdr";
+
+const CUSTOM_CONFIG_LONG_HELP: &str = r#"Filepath for config.toml file overriding
+OR adding sdr config values for soapysdr.
+
+An example of overriding the included config of `config.toml` for the rtlsdr:
+
+[[sdr]]
+driver = "rtlsdr"
+
+[[sdrs.setting]]
+key = "biastee"
+value = "true"
+
+[[sdr.gain]]
+key = "GAIN"
+value = 20.0
+"#;
+
+const HOST_PORT_ENV: &str = "HOST_PORT";
+
+#[derive(Debug, Parser)]
+#[clap(
+    version,
+    name = "ControlTower",
+    author = "Ciprian M.",
+    about = "ADS-B Demodulator and Radar, ATC Radio"
+)]
+pub struct Options {
+    /// ip address to bind with for client connections, overrides `HOST_PORT`
+    #[clap(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// port to bind with for client connections, overrides `HOST_PORT`
+    #[clap(long, default_value = "9000")]
+    pub port: u16,
+
+    /// soapysdr driver name (sdr device) from default `config.toml` or `--custom-config`
+    ///
+    /// This is used both for instructing soapysdr how to find the sdr and what sdr is being used,
+    /// as well as the key value in the `config.toml` file. This must match exactly with the
+    /// `.driver` field in order for this application to use the provided config settings.
+    #[clap(long, default_value = "rtlsdr")]
+    pub driver: String,
+
+    /// specify extra values for soapysdr driver specification
+    #[clap(long)]
+    pub driver_extra: Vec<String>,
+
+    /// read samples from a remote dongle through `rtl_tcp` at `<host>:<port>` instead of soapysdr
+    #[clap(long, value_name = "HOST:PORT", group = "source")]
+    pub rtl_tcp: Option<String>,
+
+    /// tuner gain in dB for `--rtl-tcp`, automatic gain if not given
+    #[clap(long, requires = "rtl_tcp")]
+    pub rtl_tcp_gain: Option<f64>,
+
+    /// read samples from a raw IQ file or a SigMF recording (`.sigmf-meta`/`.sigmf-data`) instead
+    /// of soapysdr
+    #[clap(long, value_name = "PATH", group = "source", group = "replayable")]
+    pub iq_file: Option<String>,
+
+    /// read raw IQ samples from stdin instead of soapysdr, for example from `rtl_sdr -`
+    #[clap(long, group = "source", group = "replayable")]
+    pub stdin: bool,
+
+    /// sample format of `--iq-file` and `--stdin`: cu8 (rtl_sdr), cs8 (hackrf_transfer),
+    /// cs16 (soapysdr, `test_iq/`) or cf32. SigMF recordings use their own
+    #[clap(long, default_value = "cs16")]
+    pub iq_format: SampleFormat,
+
+    /// replay a log of messages saved from a Beast or AVR output instead of reading samples
+    #[clap(long, value_name = "PATH", group = "source", group = "replayable")]
+    pub replay_log: Option<String>,
+
+    /// format of `--replay-log`: beast, or avr with `@<timestamp>` prefixes for pacing
+    #[clap(long, default_value = "beast", requires = "replay_log")]
+    pub replay_format: InputFormat,
+
+    /// speed of `--iq-file`, `--stdin` and `--replay-log` as a multiple of real time, like 1 or
+    /// 10, or max to go as fast as possible
+    #[clap(long, default_value = "max", requires = "replayable")]
+    pub replay_speed: ReplaySpeed,
+
+    /// record the samples to rotating SigMF files in this directory while demodulating
+    #[clap(long, value_name = "DIR", conflicts_with_all = ["net_only", "replay_log"])]
+    pub record: Option<PathBuf>,
+
+    /// start a new recording once the current one reaches this many megabytes
+    #[clap(long, requires = "record", default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+    pub record_max_mb: u64,
+
+    /// also start a new recording after this many seconds
+    #[clap(long, requires = "record", value_parser = clap::value_parser!(u64).range(1..))]
+    pub record_max_secs: Option<u64>,
+
+    /// only record the chunks of samples that messages were decoded from
+    #[clap(long, requires = "record")]
+    pub record_trigger: bool,
+
+    #[clap(long, help = CUSTOM_CONFIG_HELP, long_help = CUSTOM_CONFIG_LONG_HELP)]
+    pub custom_config: Option<String>,
+
+    /// number of bit errors to correct in DF11/DF17/DF18 using the CRC, 0 disables correction
+    ///
+    /// DF11 is only ever corrected for a single bit. Correcting two bits in DF17/DF18 builds a
+    /// larger lookup table on the first corrupted message.
+    #[clap(long, default_value = "1", value_parser = clap::value_parser!(u8).range(0..=2))]
+    pub fix: u8,
+
+    /// receiver latitude in degrees, used as the reference for local CPR position decoding
+    #[clap(long, requires = "lon", allow_hyphen_values = true, value_parser = parse_latitude)]
+    pub lat: Option<f64>,
+
+    /// receiver longitude in degrees, used as the reference for local CPR position decoding
+    #[clap(long, requires = "lat", allow_hyphen_values = true, value_parser = parse_longitude)]
+    pub lon: Option<f64>,
+
+    /// maximum range of the receiver in NM, limits how far from the receiver a position decoded
+    /// from a single frame is trusted
+    #[clap(long, default_value = "300", value_parser = parse_max_range)]
+    pub max_range: f64,
+
+    /// tcp port for Mode-S Beast binary output, 0 disables
+    #[clap(long, default_value = "30005")]
+    pub net_bo_port: u16,
+
+    /// tcp port for raw AVR (`*<hex>;`) output, 0 disables
+    #[clap(long, default_value = "30002")]
+    pub net_ro_port: u16,
+
+    /// prefix raw AVR output with the 12MHz timestamp for MLAT (`@<timestamp><hex>;`)
+    #[clap(long)]
+    pub net_ro_mlat: bool,
+
+    /// tcp port for SBS-1 (BaseStation) output, 0 disables
+    #[clap(long, default_value = "30003")]
+    pub net_sbs_port: u16,
+
+    /// connect to a remote receiver and decode its messages, `<host>:<port>,<beast|avr>`,
+    /// may be repeated
+    #[clap(long, value_name = "HOST:PORT,FORMAT")]
+    pub net_connector: Vec<Connector>,
+
+    /// tcp port accepting Mode-S Beast binary input pushed by other receivers, 0 disables
+    #[clap(long, default_value = "30004")]
+    pub net_bi_port: u16,
+
+    /// tcp port accepting raw AVR input pushed by other receivers, 0 disables
+    #[clap(long, default_value = "30001")]
+    pub net_ri_port: u16,
+
+    /// don't open an sdr, only decode messages from the network inputs
+    #[clap(long, group = "source")]
+    pub net_only: bool,
+}
+
+impl Options {
+    /// receiver location as (lat, lon), if both were given
+    pub fn receiver(&self) -> Option<(f64, f64)> {
+        self.lat.zip(self.lon)
+    }
+
+    /// soapysdr driver args, `--driver` followed by every `--driver-extra`
+    pub fn driver_args(&self) -> String {
+        let mut driver = format!("driver={}", self.driver);
+        for e in &self.driver_extra {
+            driver.push_str(&format!(",{e}"));
+        }
+        driver
+    }
+}
+
+fn parse_latitude(s: &str) -> Result<f64, String> {
+    let lat: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err("latitude must be within -90..=90".to_string());
+    }
+    Ok(lat)
+}
+
+fn parse_longitude(s: &str) -> Result<f64, String> {
+    let lon: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err("longitude must be within -180..=180".to_string());
+    }
+    Ok(lon)
+}
+
+fn parse_max_range(s: &str) -> Result<f64, String> {
+    let range: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !range.is_finite() || range <= 0.0 {
+        return Err("range must be positive".to_string());
+    }
+    Ok(range)
+}
+
+/// Validated startup configuration: command line, environment (including `.env`) and the
+/// sdr `config.toml` merged together
+#[derive(Debug)]
+pub struct Config {
+    pub options: Options,
+    /// address for the http/websocket server
+    pub addr: SocketAddr,
+    /// settings for the selected `--driver`
+    pub sdr: Sdr,
+}
+
+impl Config {
+    /// Parse the command line and merge it with the environment and sdr config files.
+    ///
+    /// Call after loading `.env`. Exits with usage on invalid arguments, like `Options::parse`.
+    pub fn load() -> Result<Self, String> {
+        let matches = Options::command().get_matches();
+        let options = Options::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+        let addr = bind_addr(&options, &matches, std::env::var(HOST_PORT_ENV).ok())?;
+        let sdr = find_sdr(&options)?;
+        Ok(Self { options, addr, sdr })
+    }
+}
+
+impl Config {
+    /// Address for one of the `--net-*-port` servers, on the same ip as the http server.
+    /// `None` if the port is 0.
+    pub fn net_addr(&self, port: u16) -> Option<SocketAddr> {
+        (port != 0).then(|| SocketAddr::new(self.addr.ip(), port))
+    }
+}
+
+// --host/--port given on the command line win over `HOST_PORT`, which wins over the defaults
+fn bind_addr(
+    options: &Options,
+    matches: &ArgMatches,
+    host_port: Option<String>,
+) -> Result<SocketAddr, String> {
+    let mut addr = match host_port {
+        Some(host_port) => SocketAddr::from_str(&host_port)
+            .map_err(|e| format!("invalid {HOST_PORT_ENV} {host_port:?}: {e}"))?,
+        None => SocketAddr::new(options.host, options.port),
+    };
+
+    let from_cli = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if from_cli("host") {
+        addr.set_ip(options.host);
+    }
+    if from_cli("port") {
+        addr.set_port(options.port);
+    }
+    Ok(addr)
+}
+
+// The compiled in config with `--custom-config` on top, and the entry for `--driver` from it
+fn find_sdr(options: &Options) -> Result<Sdr, String> {
+    // read in default compiled config
+    let mut config: SdrConfig = toml::from_str(DEFAULT_CONFIG).unwrap();
+
+    // parse config from custom filepath
+    if let Some(config_filepath) = &options.custom_config {
+        let custom_config = std::fs::read_to_string(config_filepath)
+            .map_err(|e| format!("could not read {config_filepath}: {e}"))?;
+        let custom_config: SdrConfig = toml::from_str(&custom_config)
+            .map_err(|e| format!("invalid config {config_filepath}: {e}"))?;
+        println!("[-] Read in custom config: {config_filepath}");
+        // push new configs to the front, so that the `find` method finds these first
+        for sdr in custom_config.sdrs {
+            config.sdrs.insert(0, sdr);
+        }
+    }
+
+    // check if --driver exists in config, with selected driver
+    let index = config.sdrs.iter().position(|a| a.driver == options.driver).ok_or_else(|| {
+        format!("--driver {} gain values not found in custom or default config", options.driver)
+    })?;
+    Ok(config.sdrs.swap_remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_port_precedence() {
+        let parse = |args: &[&str], host_port: Option<&str>| {
+            let matches =
+                Options::command().try_get_matches_from(["control_tower"].iter().chain(args))?;
+            let options = Options::from_arg_matches(&matches)?;
+            Ok::<_, clap::Error>(bind_addr(&options, &matches, host_port.map(String::from)))
+        };
+
+        assert_eq!(parse(&[], None).unwrap(), Ok("127.0.0.1:9000".parse().unwrap()));
+        assert_eq!(parse(&[], Some("0.0.0.0:8080")).unwrap(), Ok("0.0.0.0:8080".parse().unwrap()));
+        assert_eq!(
+            parse(&["--port", "3000"], Some("0.0.0.0:8080")).unwrap(),
+            Ok("0.0.0.0:3000".parse().unwrap())
+        );
+        assert!(parse(&[], Some("nonsense")).unwrap().is_err());
+        assert!(parse(&["--lat", "52.0"], None).is_err());
+        assert!(parse(&["--lat", "91", "--lon", "0"], None).is_err());
+    }
+
+    // Whether clap accepts the arguments
+    fn accepts(args: &[&str]) -> bool {
+        Options::try_parse_from(["control_tower"].iter().chain(args)).is_ok()
+    }
+
+    #[test]
+    fn one_sample_source() {
+        assert!(!accepts(&["--stdin", "--iq-file", "a.iq"]));
+        assert!(!accepts(&["--net-only", "--rtl-tcp", "pi:1234"]));
+    }
+
+    #[test]
+    fn iq_format() {
+        assert!(accepts(&["--stdin", "--iq-format", "cu8"]));
+        assert!(!accepts(&["--stdin", "--iq-format", "u8"]));
+    }
+
+    #[test]
+    fn max_range() {
+        assert!(accepts(&["--lat", "52.0", "--lon", "4.0", "--max-range", "150"]));
+        assert!(!accepts(&["--max-range", "0"]));
+        assert!(!accepts(&["--max-range", "-10"]));
+    }
+
+    #[test]
+    fn record_options() {
+        // recording options only with --record, which needs samples
+        assert!(accepts(&["--record", "caps", "--record-trigger", "--record-max-secs", "60"]));
+        assert!(!accepts(&["--record-trigger"]));
+        assert!(!accepts(&["--record", "caps", "--record-max-mb", "0"]));
+        assert!(!accepts(&["--record", "caps", "--net-only"]));
+    }
+
+    #[test]
+    fn replay_options() {
+        assert!(accepts(&["--replay-log", "log.avr", "--replay-format", "avr"]));
+        assert!(!accepts(&["--replay-log", "log.bin", "--iq-file", "a.iq"]));
+        assert!(!accepts(&["--replay-format", "avr"]));
+        assert!(accepts(&["--iq-file", "a.iq", "--replay-speed", "10"]));
+        assert!(!accepts(&["--iq-file", "a.iq", "--replay-speed", "0"]));
+        // live sources can't be sped up
+        assert!(!accepts(&["--replay-speed", "10"]));
+        assert!(!accepts(&["--rtl-tcp", "pi:1234", "--replay-speed", "1"]));
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use libdump1090_rs::mode_s::MessageValidator;
+use libdump1090_rs::net_io::sbs::encode_sbs;
+use libdump1090_rs::tracker::Tracker;
+use libdump1090_rs::ModesMessage;
+use tokio::sync::broadcast;
+
+use crate::webserver::parse::parse;
+
+/// Where every decoded message ends up, whether it came from the sdr or the network
+#[derive(Clone)]
+pub struct Pipeline {
+    tracker: Arc<Mutex<Tracker>>,
+    messages: broadcast::Sender<ModesMessage>,
+    sbs: broadcast::Sender<String>,
+    receiver: Option<(f64, f64)>,
+    validator: Arc<Mutex<MessageValidator>>,
+}
+
+impl Pipeline {
+    pub fn new(
+        tracker: Arc<Mutex<Tracker>>,
+        messages: broadcast::Sender<ModesMessage>,
+        sbs: broadcast::Sender<String>,
+        receiver: Option<(f64, f64)>,
+        validator: MessageValidator,
+    ) -> Self {
+        Self { tracker, messages, sbs, receiver, validator: Arc::new(Mutex::new(validator)) }
+    }
+
+    /// Publish a message that was already checked, such as by the demodulator
+    pub fn publish(&self, msg: ModesMessage) {
+        self.validator.lock().unwrap().learn(&msg);
+        self.merge(msg);
+    }
+
+    /// Publish a message demodulated by another receiver, if it passes the same checks as the
+    /// demodulator's own. Address/parity formats are only accepted from aircraft that were
+    /// heard from, by the sdr or another receiver.
+    pub fn publish_remote(&self, mut msg: ModesMessage) {
+        if self.validator.lock().unwrap().validate(&mut msg) {
+            self.merge(msg);
+        } else {
+            ::log::debug!("rejected remote message {}", hex::encode(msg.bytes()));
+        }
+    }
+
+    // Log the message, merge it into the tracker and send it to every connected client
+    //
+    // SBS lines are built from the aircraft state this message left behind, so they are
+    // formatted here before the next message for the same aircraft can be merged.
+    fn merge(&self, msg: ModesMessage) {
+        ::log::debug!("{}", parse(&msg, self.receiver));
+        let sbs = {
+            let mut tracker = self.tracker.lock().unwrap();
+            let aircraft = tracker.update(&msg);
+            aircraft
+                .filter(|_| self.sbs.receiver_count() > 0)
+                .and_then(|aircraft| encode_sbs(&msg, aircraft, SystemTime::now()))
+        };
+
+        // it's fine if there are no clients
+        if let Some(line) = sbs {
+            let _ = self.sbs.send(line);
+        }
+        let _ = self.messages.send(msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sbs_from_merged_state() {
+        let tracker = Arc::new(Mutex::new(Tracker::default()));
+        let (messages, _) = broadcast::channel(16);
+        let (sbs, mut lines) = broadcast::channel(16);
+        let pipeline = Pipeline::new(tracker, messages, sbs, None, MessageValidator::default());
+
+        // an even and odd position of one aircraft, both merged before any line is sent
+        for hex in ["8d40621d58c382d690c8ac2863a7", "8d40621d58c386435cc412692ad6"] {
+            let msg = ModesMessage::from_bytes(&hex::decode(hex).unwrap(), 0, -10.0).unwrap();
+            pipeline.publish(msg);
+        }
+
+        // only the second message completes the position
+        let fields = |line: String| line.split(',').map(str::to_string).collect::<Vec<_>>();
+        let first = fields(lines.try_recv().unwrap());
+        assert_eq!(first[1], "3");
+        assert_eq!((first[14].as_str(), first[15].as_str()), ("", ""));
+        let second = fields(lines.try_recv().unwrap());
+        assert_eq!((second[14].as_str(), second[15].as_str()), ("52.26578", "3.93891"));
+    }
+}
@@ -0,0 +1,208 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use libdump1090_rs::sigmf::{SigmfAnnotation, SigmfMeta, SigmfWriter};
+use libdump1090_rs::{ModesMessage, MODES_MAG_BUF_SAMPLES};
+use num_complex::Complex;
+
+use crate::options::Options;
+
+// Size of a sample in the recordings, `ci16_le`
+const BYTES_PER_SAMPLE: u64 = 4;
+
+/// Records the samples read from the source to rotating SigMF files in `--record`
+///
+/// Samples are collected into chunks of at least `MODES_MAG_BUF_SAMPLES`, which with
+/// `--record-trigger` are only saved if a message was decoded from them. The chunk before is saved
+/// along with them, as a message can start in its trailing samples. Every decoded message is
+/// annotated in the meta file.
+pub struct Recorder {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_duration: Option<Duration>,
+    trigger: bool,
+    frequency: f64,
+    hw: String,
+    current: Option<Recording>,
+    pending: Chunk,
+    // the last chunk skipped by the trigger, saved in front of the next one with messages
+    previous: Option<Chunk>,
+    // samples were skipped since the last saved chunk
+    gap: bool,
+}
+
+struct Recording {
+    writer: SigmfWriter,
+    started: Instant,
+}
+
+#[derive(Default)]
+struct Chunk {
+    samples: Vec<Complex<i16>>,
+    first_sample_timestamp_12mhz: u64,
+    time: Option<SystemTime>,
+    messages: Vec<ModesMessage>,
+}
+
+impl Recorder {
+    /// Create the `--record` directory. `hw` describes the source for the meta files.
+    pub fn new(options: &Options, frequency: f64, hw: String) -> Result<Option<Self>, String> {
+        let Some(dir) = &options.record else {
+            return Ok(None);
+        };
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("could not create {}: {e}", dir.display()))?;
+        println!("[-] Recording to {}", dir.display());
+
+        Ok(Some(Self {
+            dir: dir.clone(),
+            max_bytes: options.record_max_mb * 1_000_000,
+            max_duration: options.record_max_secs.map(Duration::from_secs),
+            trigger: options.record_trigger,
+            frequency,
+            hw,
+            current: None,
+            pending: Chunk::default(),
+            previous: None,
+            gap: false,
+        }))
+    }
+
+    /// Add samples that were just read, along with the messages demodulated from them.
+    ///
+    /// `first_sample_timestamp_12mhz` is the demodulator timestamp of the first sample.
+    pub fn record(
+        &mut self,
+        samples: &[Complex<i16>],
+        first_sample_timestamp_12mhz: u64,
+        messages: &[ModesMessage],
+    ) -> io::Result<()> {
+        if self.pending.samples.is_empty() {
+            self.pending.first_sample_timestamp_12mhz = first_sample_timestamp_12mhz;
+            self.pending.time = Some(SystemTime::now());
+        }
+        self.pending.samples.extend_from_slice(samples);
+        self.pending.messages.extend_from_slice(messages);
+
+        if self.pending.samples.len() >= MODES_MAG_BUF_SAMPLES {
+            self.save_pending()?;
+        }
+        Ok(())
+    }
+
+    /// Save what is left and close the current recording
+    pub fn finish(mut self) -> io::Result<()> {
+        self.save_pending()?;
+        if let Some(recording) = self.current.take() {
+            recording.writer.finish()?;
+        }
+        Ok(())
+    }
+
+    fn save_pending(&mut self) -> io::Result<()> {
+        let mut chunk = std::mem::take(&mut self.pending);
+        if chunk.samples.is_empty() {
+            return Ok(());
+        }
+        if self.trigger {
+            if chunk.messages.is_empty() {
+                if self.previous.replace(chunk).is_some() {
+                    self.gap = true;
+                }
+                return Ok(());
+            }
+            if let Some(mut previous) = self.previous.take() {
+                previous.samples.extend_from_slice(&chunk.samples);
+                previous.messages = chunk.messages;
+                chunk = previous;
+            }
+        }
+        let time = chunk.time.unwrap_or_else(SystemTime::now);
+
+        let recording = match &mut self.current {
+            Some(recording) => {
+                if std::mem::take(&mut self.gap) {
+                    recording.writer.new_capture(time);
+                }
+                recording
+            }
+            None => {
+                self.gap = false;
+                self.current.insert(self.create(time)?)
+            }
+        };
+
+        let offset = recording.writer.samples_written();
+        recording.writer.write_samples(&chunk.samples)?;
+        for msg in &chunk.messages {
+            let mut annotation =
+                SigmfAnnotation::for_message(msg, chunk.first_sample_timestamp_12mhz);
+            annotation.sample_start += offset;
+            recording.writer.annotate(annotation);
+        }
+
+        let full = recording.writer.samples_written() * BYTES_PER_SAMPLE >= self.max_bytes;
+        let expired = self.max_duration.is_some_and(|max| recording.started.elapsed() >= max);
+        if full || expired {
+            if let Some(recording) = self.current.take() {
+                recording.writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    // A new recording named after the time of its first sample
+    fn create(&self, time: SystemTime) -> io::Result<Recording> {
+        let millis = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let base = self.dir.join(format!("capture_{millis}"));
+        println!("[-] Recording {}", base.display());
+
+        let meta = SigmfMeta::new(self.frequency, Some(self.hw.clone()), time);
+        let writer = SigmfWriter::create(base, meta)?;
+        Ok(Recording { writer, started: Instant::now() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use libdump1090_rs::sigmf::{read_meta, SIGMF_META_EXTENSION};
+
+    use super::*;
+
+    #[test]
+    fn trigger_keeps_previous_chunk() {
+        let dir = std::env::temp_dir().join(format!("record_trigger_{}", std::process::id()));
+        let dir_arg = dir.to_str().unwrap();
+        let options =
+            Options::try_parse_from(["control_tower", "--record", dir_arg, "--record-trigger"])
+                .unwrap();
+        let mut recorder =
+            Recorder::new(&options, 1_090_000_000.0, "test".into()).unwrap().unwrap();
+
+        let n = MODES_MAG_BUF_SAMPLES;
+        let samples = vec![Complex::new(0, 0); n];
+        let chunk_ts = |i: u64| i * n as u64 * 5;
+        // the first is dropped, the second saved in front of the next chunk
+        recorder.record(&samples, chunk_ts(0), &[]).unwrap();
+        recorder.record(&samples, chunk_ts(1), &[]).unwrap();
+        // the preamble starts 100 samples before the chunk it was decoded from
+        let ts = chunk_ts(2) - 100 * 5 + (8 + 56) * 12;
+        let msg = ModesMessage::from_bytes(&[0x5d, 0xad, 0x92, 0x93, 0x62, 0x65, 0xf5], ts, -10.0);
+        recorder.record(&samples, chunk_ts(2), &[msg.unwrap()]).unwrap();
+        recorder.finish().unwrap();
+
+        let meta_path = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == SIGMF_META_EXTENSION))
+            .unwrap();
+        let meta = read_meta(&meta_path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(meta.captures.len(), 1);
+        assert_eq!(meta.annotations.len(), 1);
+        assert_eq!(meta.annotations[0].sample_start, n as u64 - 100);
+    }
+}
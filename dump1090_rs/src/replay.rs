@@ -0,0 +1,199 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use libdump1090_rs::net_io::avr::decode_avr;
+use libdump1090_rs::net_io::beast::BeastDecoder;
+use libdump1090_rs::ModesMessage;
+
+use crate::net_input::InputFormat;
+use crate::options::Options;
+use crate::pipeline::Pipeline;
+
+// Ticks per second of `ModesMessage::timestamp_12mhz`
+const TIMESTAMP_HZ: u64 = 12_000_000;
+
+/// How fast recordings are replayed, `--replay-speed`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    /// As fast as they can be read and demodulated
+    Max,
+    /// This multiple of real time
+    Factor(f64),
+}
+
+impl fmt::Display for ReplaySpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Max => write!(f, "max"),
+            Self::Factor(factor) => write!(f, "{factor}x"),
+        }
+    }
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "max" {
+            return Ok(Self::Max);
+        }
+        match s.strip_suffix('x').unwrap_or(s).parse::<f64>() {
+            Ok(factor) if factor > 0.0 && factor.is_finite() => Ok(Self::Factor(factor)),
+            _ => Err(format!("expected a speed-up like 1 or 10, or max, got {s:?}")),
+        }
+    }
+}
+
+/// Holds replayed data back until the time it was recorded at, scaled by the speed
+pub struct Pacer {
+    speed: ReplaySpeed,
+    start: Instant,
+}
+
+impl Pacer {
+    /// Start the clock of the recording now
+    pub fn new(speed: ReplaySpeed) -> Self {
+        Self { speed, start: Instant::now() }
+    }
+
+    /// Block until `at`, the time since the start of the recording, is reached
+    pub fn wait(&self, at: Duration) {
+        if let ReplaySpeed::Factor(factor) = self.speed {
+            let due = self.start + at.div_f64(factor);
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+        }
+    }
+}
+
+/// A log of messages to replay with `--replay-log`, as saved from a Beast or AVR output
+///
+/// Messages are paced by their 12MHz timestamps. Ones without, like AVR lines without `@`, are
+/// published right after the message before them.
+pub struct LogReplay {
+    path: String,
+    file: BufReader<File>,
+    format: InputFormat,
+    speed: ReplaySpeed,
+}
+
+impl LogReplay {
+    /// Open `--replay-log`, if given
+    pub fn open(options: &Options) -> Result<Option<Self>, String> {
+        let Some(path) = &options.replay_log else {
+            return Ok(None);
+        };
+        let file = File::open(path).map_err(|e| format!("could not open {path}: {e}"))?;
+        println!(
+            "[-] Replaying {} messages from {path} at {}",
+            options.replay_format, options.replay_speed
+        );
+        Ok(Some(Self {
+            path: path.clone(),
+            file: BufReader::new(file),
+            format: options.replay_format,
+            speed: options.replay_speed,
+        }))
+    }
+
+    /// Publish every message of the log to `pipeline`, blocking until the end
+    pub fn run(self, pipeline: &Pipeline) -> Result<(), String> {
+        replay(self.file, self.format, &Pacer::new(self.speed), |msg| pipeline.publish(msg))
+            .map_err(|e| format!("could not read {}: {e}", self.path))
+    }
+}
+
+// Decode and pace the messages from `reader`, handing each one to `publish`
+fn replay<R: BufRead>(
+    mut reader: R,
+    format: InputFormat,
+    pacer: &Pacer,
+    mut publish: impl FnMut(ModesMessage),
+) -> io::Result<()> {
+    let mut first = None;
+    let mut paced = |msg: ModesMessage| {
+        if msg.timestamp_12mhz != 0 {
+            let first = *first.get_or_insert(msg.timestamp_12mhz);
+            // timestamps from another receiver or after a restart start over
+            let ticks = msg.timestamp_12mhz.saturating_sub(first);
+            pacer.wait(Duration::from_nanos(ticks * 1_000_000_000 / TIMESTAMP_HZ));
+        }
+        publish(msg);
+    };
+
+    match format {
+        InputFormat::Beast => {
+            let mut decoder = BeastDecoder::new();
+            let mut buf = vec![0; 4096];
+            loop {
+                let len = match reader.read(&mut buf) {
+                    Ok(0) => return Ok(()),
+                    Ok(len) => len,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                for msg in decoder.decode(&buf[..len]) {
+                    paced(msg);
+                }
+            }
+        }
+        InputFormat::Avr => {
+            for line in reader.lines() {
+                let line = line?;
+                match decode_avr(&line) {
+                    Ok(msg) => paced(msg),
+                    Err(e) => ::log::debug!("ignoring avr line {line:?}: {e}"),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_speed_parsing() {
+        assert_eq!("max".parse(), Ok(ReplaySpeed::Max));
+        assert_eq!("1".parse(), Ok(ReplaySpeed::Factor(1.0)));
+        assert_eq!("2.5x".parse(), Ok(ReplaySpeed::Factor(2.5)));
+        assert!("0".parse::<ReplaySpeed>().is_err());
+        assert!("-1".parse::<ReplaySpeed>().is_err());
+        assert!("fast".parse::<ReplaySpeed>().is_err());
+    }
+
+    #[test]
+    fn paced_avr_log() {
+        // 0.3s between the timestamped messages, and one without a timestamp
+        let log = "@0000000F42408d4840d6202cc371c32ce0576098;\n\
+                   garbage\n\
+                   *02e1971ce17c84;\n\
+                   @0000004630C08d4840d6202cc371c32ce0576098;\n";
+
+        let replay_all = |speed| {
+            let start = Instant::now();
+            let mut messages = vec![];
+            replay(log.as_bytes(), InputFormat::Avr, &Pacer::new(speed), |msg| {
+                messages.push((msg.timestamp_12mhz, start.elapsed()));
+            })
+            .unwrap();
+            messages
+        };
+
+        let messages = replay_all(ReplaySpeed::Factor(2.0));
+        let timestamps: Vec<_> = messages.iter().map(|(ts, _)| *ts).collect();
+        assert_eq!(timestamps, [1_000_000, 0, 4_600_000]);
+        assert!(messages[1].1 < Duration::from_millis(100));
+        assert!(messages[2].1 >= Duration::from_millis(150));
+
+        let messages = replay_all(ReplaySpeed::Max);
+        assert!(messages[2].1 < Duration::from_millis(100));
+    }
+}
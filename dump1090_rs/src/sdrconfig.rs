@@ -1,7 +1,7 @@
 use serde::Deserialize;
 
 // Default config for the SDR from config.toml file
-pub const DEFAULT_CONFIG: &str = include_str!("../../config.toml");
+pub const DEFAULT_CONFIG: &str = include_str!("../config.toml");
 #[derive(Debug, Deserialize)]
 pub struct SdrConfig {
     pub sdrs: Vec<Sdr>,
@@ -0,0 +1,243 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::time::Duration;
+
+use libdump1090_rs::demod_2400::{Demodulator, DemodulatorConfig};
+use libdump1090_rs::rtl_tcp::{RtlTcpClient, RtlTcpGain};
+use libdump1090_rs::sigmf;
+use libdump1090_rs::source::{RawIqSource, SampleSource};
+use libdump1090_rs::MODES_MAG_BUF_SAMPLES;
+use num_complex::Complex;
+use soapysdr::{Direction, RxStream};
+
+use crate::options::{Config, Options};
+use crate::pipeline::Pipeline;
+use crate::record::Recorder;
+use crate::replay::{Pacer, ReplaySpeed};
+
+const DIRECTION: Direction = Direction::Rx;
+
+const FREQUENCY: u32 = 1_090_000_000;
+const SAMPLE_RATE: u32 = 2_400_000;
+
+/// An opened sample source and a demodulator for it
+pub struct SampleReader {
+    source: Box<dyn SampleSource + Send>,
+    demodulator: Demodulator,
+    recorder: Option<Recorder>,
+    // `--replay-speed` for files and pipes, sdrs set their own pace
+    replay_speed: Option<ReplaySpeed>,
+}
+
+impl SampleReader {
+    /// Open the source selected on the command line: `--iq-file`, `--stdin`, the remote dongle
+    /// at `--rtl-tcp`, or by default the soapysdr device for `--driver` with the settings from
+    /// its `config.toml` entry
+    pub fn open(config: &Config) -> Result<Self, String> {
+        let options = &config.options;
+        let source: Box<dyn SampleSource + Send> = if let Some(path) = &options.iq_file {
+            if sigmf::is_sigmf(path) {
+                Box::new(open_sigmf(path)?)
+            } else {
+                println!("[-] Reading {} samples from {path}", options.iq_format);
+                Box::new(
+                    RawIqSource::open(path, options.iq_format)
+                        .map_err(|e| format!("could not open {path}: {e}"))?,
+                )
+            }
+        } else if options.stdin {
+            println!("[-] Reading {} samples from stdin", options.iq_format);
+            Box::new(RawIqSource::stdin(options.iq_format))
+        } else if let Some(addr) = &options.rtl_tcp {
+            Box::new(open_rtl_tcp(addr, options)?)
+        } else {
+            Box::new(SoapySource::open(config)?)
+        };
+
+        let demodulator = Demodulator::new(DemodulatorConfig {
+            nfix_crc: usize::from(options.fix),
+            ..DemodulatorConfig::default()
+        });
+
+        let recorder = Recorder::new(options, f64::from(FREQUENCY), describe_source(config))?;
+
+        let replay_speed =
+            (options.iq_file.is_some() || options.stdin).then_some(options.replay_speed);
+
+        Ok(Self { source, demodulator, recorder, replay_speed })
+    }
+
+    /// Demodulate until the source ends, publishing every message to `pipeline` and recording
+    /// the samples with `--record`.
+    ///
+    /// This blocks, so run it on a blocking thread. Sdrs never end, they return an error.
+    pub fn run(mut self, pipeline: &Pipeline) -> Result<(), String> {
+        let record_error = |e: io::Error| format!("could not record samples: {e}");
+        let mut buf = vec![Complex::new(0, 0); MODES_MAG_BUF_SAMPLES];
+        let pacer = self.replay_speed.map(Pacer::new);
+        let mut samples_read = 0_u64;
+        loop {
+            let len = match self.source.read_samples(&mut buf) {
+                Ok(0) => {
+                    if let Some(recorder) = self.recorder {
+                        recorder.finish().map_err(record_error)?;
+                    }
+                    return Ok(());
+                }
+                Ok(len) => len,
+                Err(e) => return Err(format!("could not read samples: {e}")),
+            };
+
+            // hold the samples back until they would have been received
+            samples_read += len as u64;
+            if let Some(pacer) = &pacer {
+                pacer.wait(Duration::from_secs_f64(samples_read as f64 / f64::from(SAMPLE_RATE)));
+            }
+
+            // demodulate new data
+            let first_sample = self.demodulator.next_sample_timestamp_12mhz();
+            let resulting_data = self
+                .demodulator
+                .demodulate_samples(&buf[..len])
+                .map_err(|e| format!("could not demodulate samples: {e}"))?;
+            if let Some(recorder) = &mut self.recorder {
+                recorder
+                    .record(&buf[..len], first_sample, &resulting_data)
+                    .map_err(record_error)?;
+            }
+            for a in resulting_data {
+                pipeline.publish(a);
+            }
+        }
+    }
+}
+
+/// A soapysdr device streaming samples
+struct SoapySource {
+    stream: RxStream<Complex<i16>>,
+}
+
+impl SoapySource {
+    // Open the device for `--driver` and apply the settings from its `config.toml` entry
+    fn open(config: &Config) -> Result<Self, String> {
+        let mut stream = open_soapy(config)?;
+        stream.activate(None).map_err(|e| format!("Soapysdr error: {e}"))?;
+        Ok(Self { stream })
+    }
+}
+
+impl SampleSource for SoapySource {
+    fn read_samples(&mut self, buf: &mut [Complex<i16>]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(&mut [buf], 5_000_000) {
+                Ok(len) => return Ok(len),
+                // a timeout means the sdr is gone, other errors like overflows are not fatal
+                Err(e) if matches!(e.code, soapysdr::ErrorCode::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "could not read SDR device",
+                    ));
+                }
+                Err(e) => ::log::warn!("sdr read failed: {e}"),
+            }
+        }
+    }
+}
+
+// What the samples come from, for the `--record` meta files
+fn describe_source(config: &Config) -> String {
+    let options = &config.options;
+    if let Some(path) = &options.iq_file {
+        format!("file {path}")
+    } else if options.stdin {
+        format!("{} samples from stdin", options.iq_format)
+    } else if let Some(addr) = &options.rtl_tcp {
+        let gain = options.rtl_tcp_gain.map_or_else(|| "auto".to_string(), |g| format!("{g} dB"));
+        format!("rtl_tcp {addr}, gain {gain}")
+    } else {
+        let mut hw = format!("soapysdr {}", options.driver_args());
+        for gain in &config.sdr.gain {
+            hw.push_str(&format!(", {}={}", gain.key, gain.value));
+        }
+        hw
+    }
+}
+
+// Open a SigMF recording, which describes its own sample format
+fn open_sigmf(path: &str) -> Result<RawIqSource<BufReader<File>>, String> {
+    let (meta, source) = sigmf::open(path).map_err(|e| format!("could not open {path}: {e}"))?;
+    println!("[-] Reading {} samples from {path}", meta.global.datatype);
+    if let Some(hw) = &meta.global.hw {
+        println!("[-] Recorded with: {hw}");
+    }
+    if let Some(capture) = meta.captures.first() {
+        println!(
+            "[-] Recorded at: {}, {} Hz",
+            capture.datetime.as_deref().unwrap_or("unknown time"),
+            capture.frequency.map_or_else(|| "unknown".to_string(), |f| f.to_string())
+        );
+    }
+    Ok(source)
+}
+
+// Connect to `rtl_tcp` and tune it like the local rtlsdr
+fn open_rtl_tcp(addr: &str, options: &Options) -> Result<RtlTcpClient, String> {
+    let rtl_tcp_error = |e: std::io::Error| format!("rtl_tcp error: {e}");
+
+    println!("[-] Connecting to rtl_tcp: {addr}");
+    let mut client = RtlTcpClient::connect(addr).map_err(rtl_tcp_error)?;
+    println!("[-] Dongle info: {:?}", client.info());
+
+    client.set_frequency(FREQUENCY).map_err(rtl_tcp_error)?;
+    client.set_sample_rate(SAMPLE_RATE).map_err(rtl_tcp_error)?;
+    let gain = options.rtl_tcp_gain.map_or(RtlTcpGain::Auto, RtlTcpGain::Manual);
+    println!("[-] Gain: {gain:?}");
+    client.set_gain(gain).map_err(rtl_tcp_error)?;
+    Ok(client)
+}
+
+// Open the soapysdr device for `--driver` and apply the settings from its `config.toml` entry
+fn open_soapy(config: &Config) -> Result<RxStream<Complex<i16>>, String> {
+    let sdr_error = |e: soapysdr::Error| format!("Soapysdr error: {e}");
+    let options = &config.options;
+    let sdr = &config.sdr;
+
+    // setup soapysdr driver
+    let driver = options.driver_args();
+    println!("[-] Using soapysdr driver_args: {driver}");
+    let d = soapysdr::Device::new(&*driver).map_err(sdr_error)?;
+
+    println!("[-] Using config: {sdr:#?}");
+    // set user defined config settings
+    let channel = sdr.channel;
+
+    for gain in &sdr.gain {
+        println!("[-] Writing gain: {} = {}", gain.key, gain.value);
+        d.set_gain_element(DIRECTION, channel, &*gain.key, gain.value).map_err(sdr_error)?;
+    }
+    if let Some(setting) = &sdr.setting {
+        for setting in setting {
+            println!("[-] Writing setting: {} = {}", setting.key, setting.value);
+            d.write_setting(&*setting.key, &*setting.value).map_err(sdr_error)?;
+            println!(
+                "[-] Reading setting: {} = {}",
+                setting.key,
+                d.read_setting(&*setting.key).map_err(sdr_error)?
+            );
+        }
+    }
+
+    if let Some(antenna) = &sdr.antenna {
+        println!("[-] Setting antenna: {}", antenna.name);
+        d.set_antenna(DIRECTION, channel, antenna.name.clone()).map_err(sdr_error)?;
+    }
+
+    // now we set defaults
+    d.set_frequency(DIRECTION, channel, f64::from(FREQUENCY), ()).map_err(sdr_error)?;
+    println!("[-] Frequency: {:?}", d.frequency(DIRECTION, channel));
+
+    d.set_sample_rate(DIRECTION, channel, f64::from(SAMPLE_RATE)).map_err(sdr_error)?;
+    println!("[-] Sample rate: {:?}", d.sample_rate(DIRECTION, 0));
+
+    d.rx_stream::<Complex<i16>>(&[channel]).map_err(sdr_error)
+}
@@ -12,7 +12,7 @@ console.log("Hello from the frontend! 🚀");
 document.addEventListener("DOMContentLoaded", (_event) => {
   console.log("DOM fully loaded and parsed!");
   // Create a WebSocket connection
-  const socket = new WebSocket("ws://127.0.0.1:9000/echo");
+  const socket = new WebSocket(`ws://${location.host}/echo`);
   // Connection opened
   socket.addEventListener("open", (_event) => {
     console.log("WebSocket is open now.");
@@ -74,6 +74,13 @@ function hexToBin(hexString) {
 // Main function to parse hex message
 function parse(hexMessage) {
   let binaryMessage = hexToBin(hexMessage);
+  // Short (56-bit) replies don't carry an extended squitter payload
+  if (binaryMessage.length === 56) {
+    return {
+      downlinkFormat: parseInt(binaryMessage.slice(0, 5), 2),
+      typeDesc: "Short Reply",
+    };
+  }
   // Ensure the binary message is 112 bits long
   if (binaryMessage.length !== 112) {
     console.log("Invalid ADS-B message length.");
@@ -283,19 +290,26 @@ function decodeVelocity(bits) {
   return parseInt(velocityBits, 2) || 0;
 }
 
-// Data store to group entries by ICAO address
-const icaoDataStore = {};
-
-// Function to update the data store with parsed data
-function updateDataStore(parsedData) {
-  let { icao, data } = parsedData;
-
-  if (!icaoDataStore[icao]) {
-    icaoDataStore[icao] = [];
+// Aircraft state by ICAO address, as tracked by the server
+let icaoDataStore = {};
+const AIRCRAFT_POLL_INTERVAL = 1000;
+
+// Function to refresh the data store from the server side tracker
+async function updateDataStore() {
+  try {
+    const response = await fetch("aircraft.json");
+    const data = await response.json();
+    icaoDataStore = {};
+    for (const aircraft of data.aircraft) {
+      icaoDataStore[aircraft.hex] = aircraft;
+    }
+    document.getElementById("subtitle").textContent =
+      `Tracking ${data.aircraft.length} aircraft`;
+  } catch (error) {
+    console.error("[!] Could not fetch aircraft: ", error);
   }
-  icaoDataStore[icao].push(data);
-  console.log("[-] Updated data store: ", icaoDataStore);
 }
+setInterval(updateDataStore, AIRCRAFT_POLL_INTERVAL);
 
 // Update the UI function
 let updateTriggeredCount = 0;
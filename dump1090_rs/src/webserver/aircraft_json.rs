@@ -0,0 +1,106 @@
+use std::time::Instant;
+
+use libdump1090_rs::mode_s::{AltitudeUnit, Emergency};
+use libdump1090_rs::tracker::{Aircraft, Tracked, Tracker};
+use serde::Serialize;
+
+// Layout loosely follows the aircraft.json written by dump1090
+#[derive(Debug, Serialize)]
+pub struct AircraftJson {
+    /// total messages received from the aircraft currently tracked
+    messages: u64,
+    aircraft: Vec<AircraftEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct AircraftEntry {
+    hex: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flight: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    squawk: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emergency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alt_baro: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alt_geom: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_ground: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    track: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    airspeed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heading: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vert_rate: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lat: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lon: Option<f64>,
+    /// seconds since the position was last updated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seen_pos: Option<f64>,
+    /// seconds since any message was received
+    seen: f64,
+    messages: u64,
+    rssi: f64,
+}
+
+// Lowest signal level reported, in dBFS
+const MIN_RSSI: f64 = -49.5;
+
+fn value<T: Clone>(field: &Option<Tracked<T>>) -> Option<T> {
+    field.as_ref().map(|f| f.value.clone())
+}
+
+impl AircraftEntry {
+    fn new(a: &Aircraft, now: Instant) -> Self {
+        let geom = a.gnss_height.map(|h| h.value.in_feet()).or_else(|| {
+            // derive the geometric altitude from the baro altitude and the reported difference
+            let baro = a.altitude?.value;
+            (baro.unit == AltitudeUnit::Feet).then_some(baro.value + a.gnss_baro_diff?.value)
+        });
+        let emergency = a.emergency.and_then(|e| match e.value {
+            Emergency::None => None,
+            e => Some(format!("{e:?}")),
+        });
+
+        Self {
+            hex: format!("{:06x}", a.address),
+            flight: value(&a.callsign),
+            category: a.category.map(|c| format!("{:02X}", c.value)),
+            squawk: a.squawk.map(|s| s.value.to_string()),
+            emergency,
+            alt_baro: a.altitude.map(|alt| alt.value.in_feet()),
+            alt_geom: geom,
+            on_ground: value(&a.on_ground),
+            gs: value(&a.ground_speed),
+            track: value(&a.track),
+            airspeed: value(&a.airspeed),
+            heading: value(&a.heading),
+            vert_rate: a.vertical_rate.map(|v| v.value.0),
+            lat: a.position.map(|p| p.value.0),
+            lon: a.position.map(|p| p.value.1),
+            seen_pos: a.position.map(|p| now.duration_since(p.updated).as_secs_f64()),
+            seen: now.duration_since(a.last_seen).as_secs_f64(),
+            messages: a.messages,
+            // network inputs may not carry a signal level, floor it like dump1090
+            rssi: a.rssi.max(MIN_RSSI),
+        }
+    }
+}
+
+impl AircraftJson {
+    pub fn new(tracker: &Tracker) -> Self {
+        let now = Instant::now();
+        let mut aircraft: Vec<_> = tracker.aircraft().map(|a| AircraftEntry::new(a, now)).collect();
+        aircraft.sort_by(|a, b| a.hex.cmp(&b.hex));
+        Self { messages: tracker.aircraft().map(|a| a.messages).sum(), aircraft }
+    }
+}
@@ -1,3 +1,2 @@
+pub mod aircraft_json;
 pub mod parse;
-pub mod read_loop;
-pub mod sdrconfig;
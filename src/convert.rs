@@ -0,0 +1,134 @@
+// Raw IQ formats of common sdr tools, like convert.c
+
+use std::fmt;
+use std::str::FromStr;
+
+use num_complex::Complex;
+
+use crate::MagnitudeBuffer;
+
+/// Layout of interleaved I/Q samples
+///
+/// Every format is scaled to the same full scale, so a recording converted between formats
+/// gives the same [`MagnitudeBuffer`] apart from quantization.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Unsigned 8 bit centered on 127.5, from `rtl_sdr` and `rtl_tcp`
+    Cu8,
+    /// Signed 8 bit, from `hackrf_transfer`
+    Cs8,
+    /// Signed 16 bit little endian, from soapysdr and the files in `test_iq/`
+    Cs16,
+    /// 32 bit little endian float in -1.0..1.0, from GNU Radio and SigMF tools
+    Cf32,
+}
+
+impl SampleFormat {
+    /// Size of one I/Q pair in bytes
+    #[must_use]
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::Cu8 | Self::Cs8 => 2,
+            Self::Cs16 => 4,
+            Self::Cf32 => 8,
+        }
+    }
+
+    /// Convert whole samples from `raw` to the signed 16 bit samples the demodulator takes,
+    /// returning how many were written to `out`
+    ///
+    /// 8 bit samples convert exactly, floats are rounded and clipped.
+    pub fn to_cs16(self, raw: &[u8], out: &mut [Complex<i16>]) -> usize {
+        let mut len = 0;
+        for (iq, sample) in raw.chunks_exact(self.bytes_per_sample()).zip(out) {
+            *sample = match self {
+                Self::Cu8 => {
+                    let scale = |x: u8| (i16::from(x) - 128) * 256 + 128;
+                    Complex::new(scale(iq[0]), scale(iq[1]))
+                }
+                Self::Cs8 => {
+                    let scale = |x: u8| i16::from(x as i8) * 256;
+                    Complex::new(scale(iq[0]), scale(iq[1]))
+                }
+                Self::Cs16 => Complex::new(
+                    i16::from_le_bytes([iq[0], iq[1]]),
+                    i16::from_le_bytes([iq[2], iq[3]]),
+                ),
+                Self::Cf32 => {
+                    let (i, q) = self.normalized(iq);
+                    let scale = |x: f32| (x * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+                    Complex::new(scale(i), scale(q))
+                }
+            };
+            len += 1;
+        }
+        len
+    }
+
+    /// Magnitudes of all whole samples in `raw`
+    #[must_use]
+    pub fn to_mag(self, raw: &[u8]) -> MagnitudeBuffer {
+        let mut outbuf = MagnitudeBuffer::default();
+        self.to_mag_into(raw, &mut outbuf);
+        outbuf
+    }
+
+    /// Append the magnitude of every whole sample in `raw` to `outbuf`, which must have room
+    /// for all of them
+    pub fn to_mag_into(self, raw: &[u8], outbuf: &mut MagnitudeBuffer) {
+        for iq in raw.chunks_exact(self.bytes_per_sample()) {
+            let (fi, fq) = self.normalized(iq);
+            // same rounding as `utils::to_mag_into`
+            let mag = f32::sqrt(fq.mul_add(fq, fi * fi));
+            outbuf.push(mag.mul_add(f32::from(u16::MAX), 0.5) as u16);
+        }
+    }
+
+    // One sample as I and Q, with full scale at 1.0
+    fn normalized(self, iq: &[u8]) -> (f32, f32) {
+        match self {
+            Self::Cu8 => {
+                let scale = |x: u8| (f32::from(x) - 127.5) / 128.0;
+                (scale(iq[0]), scale(iq[1]))
+            }
+            Self::Cs8 => {
+                let scale = |x: u8| f32::from(x as i8) / 128.0;
+                (scale(iq[0]), scale(iq[1]))
+            }
+            Self::Cs16 => {
+                let scale = |b: [u8; 2]| f32::from(i16::from_le_bytes(b)) / 32768.0;
+                (scale([iq[0], iq[1]]), scale([iq[2], iq[3]]))
+            }
+            Self::Cf32 => (
+                f32::from_le_bytes([iq[0], iq[1], iq[2], iq[3]]),
+                f32::from_le_bytes([iq[4], iq[5], iq[6], iq[7]]),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Cu8 => "cu8",
+            Self::Cs8 => "cs8",
+            Self::Cs16 => "cs16",
+            Self::Cf32 => "cf32",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for SampleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cu8" => Ok(Self::Cu8),
+            "cs8" => Ok(Self::Cs8),
+            "cs16" => Ok(Self::Cs16),
+            "cf32" => Ok(Self::Cf32),
+            _ => Err(format!("unknown sample format {s:?}, expected cu8, cs8, cs16 or cf32")),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+// This module includes functionality translated from cpr.c
+
+use crate::mode_s::CprFrame;
+
+// 2^17, the range of the encoded latitude and longitude
+const CPR_SCALE: f64 = 131_072.0;
+
+// cpr.c:cprModInt
+fn cpr_mod_int(a: i32, b: i32) -> i32 {
+    a.rem_euclid(b)
+}
+
+// cpr.c:cprModDouble
+fn cpr_mod_double(a: f64, b: f64) -> f64 {
+    let res = a % b;
+    if res < 0.0 {
+        res + b
+    } else {
+        res
+    }
+}
+
+// cpr.c:cprNLFunction
+// Number of longitude zones at a latitude, the table is symmetric about the equator
+fn cpr_nl(lat: f64) -> i32 {
+    const NL_TABLE: [f64; 58] = [
+        10.470_471_30,
+        14.828_174_37,
+        18.186_263_57,
+        21.029_394_93,
+        23.545_044_87,
+        25.829_247_07,
+        27.938_987_10,
+        29.911_356_86,
+        31.772_097_08,
+        33.539_934_36,
+        35.228_995_98,
+        36.850_251_08,
+        38.412_418_92,
+        39.922_566_84,
+        41.386_518_32,
+        42.809_140_12,
+        44.194_549_51,
+        45.546_267_23,
+        46.867_332_52,
+        48.160_391_28,
+        49.427_764_39,
+        50.671_501_66,
+        51.893_424_69,
+        53.095_161_53,
+        54.278_174_72,
+        55.443_784_44,
+        56.593_187_56,
+        57.727_473_54,
+        58.847_637_76,
+        59.954_592_77,
+        61.049_177_74,
+        62.132_166_59,
+        63.204_274_79,
+        64.266_165_23,
+        65.318_453_10,
+        66.361_710_08,
+        67.396_467_74,
+        68.423_220_22,
+        69.442_426_31,
+        70.454_510_75,
+        71.459_864_73,
+        72.458_845_45,
+        73.451_774_42,
+        74.438_934_16,
+        75.420_562_57,
+        76.396_843_91,
+        77.367_894_61,
+        78.333_740_83,
+        79.294_282_25,
+        80.249_232_13,
+        81.198_013_49,
+        82.139_569_81,
+        83.071_994_45,
+        83.991_735_63,
+        84.891_661_91,
+        85.755_416_21,
+        86.535_369_98,
+        87.000_000_00,
+    ];
+
+    let lat = lat.abs();
+    let zones_below = NL_TABLE.iter().take_while(|&&limit| lat >= limit).count();
+    59 - zones_below as i32
+}
+
+// cpr.c:cprNFunction
+fn cpr_n(lat: f64, odd: bool) -> i32 {
+    (cpr_nl(lat) - i32::from(odd)).max(1)
+}
+
+// cpr.c:cprDlonFunction
+fn cpr_dlon(lat: f64, odd: bool, surface: bool) -> f64 {
+    (if surface { 90.0 } else { 360.0 }) / f64::from(cpr_n(lat, odd))
+}
+
+fn check_pair(even: &CprFrame, odd: &CprFrame) -> Result<(), &'static str> {
+    if even.odd || !odd.odd {
+        return Err("cpr: expected one even and one odd frame");
+    }
+    Ok(())
+}
+
+// Shared part of the airborne and surface global decode: latitude of both frames
+fn global_latitudes(even: &CprFrame, odd: &CprFrame, dlat_range: f64) -> (f64, f64) {
+    let lat0 = f64::from(even.lat);
+    let lat1 = f64::from(odd.lat);
+
+    // Compute the Latitude Index "j"
+    let j = (((59.0 * lat0 - 60.0 * lat1) / CPR_SCALE) + 0.5).floor() as i32;
+    let rlat0 = dlat_range / 60.0 * (f64::from(cpr_mod_int(j, 60)) + lat0 / CPR_SCALE);
+    let rlat1 = dlat_range / 59.0 * (f64::from(cpr_mod_int(j, 59)) + lat1 / CPR_SCALE);
+    (rlat0, rlat1)
+}
+
+// Shared part of the airborne and surface global decode: longitude from the latest frame
+fn global_longitude(
+    even: &CprFrame,
+    odd: &CprFrame,
+    rlat: f64,
+    odd_is_latest: bool,
+    surface: bool,
+) -> f64 {
+    let lon0 = f64::from(even.lon);
+    let lon1 = f64::from(odd.lon);
+    let nl = f64::from(cpr_nl(rlat));
+
+    // Compute ni and the Longitude Index "m"
+    let ni = cpr_n(rlat, odd_is_latest);
+    let m = (((lon0 * (nl - 1.0)) - (lon1 * nl)) / CPR_SCALE + 0.5).floor() as i32;
+    let lon = if odd_is_latest { lon1 } else { lon0 };
+    cpr_dlon(rlat, odd_is_latest, surface) * (f64::from(cpr_mod_int(m, ni)) + lon / CPR_SCALE)
+}
+
+// cpr.c:decodeCPRairborne
+/// Globally unambiguous airborne position from an even and an odd frame
+///
+/// `odd_is_latest` selects which frame the returned position belongs to, normally the one that
+/// was received most recently. The frames should be no more than about 10 seconds apart, as
+/// this function can't tell if the aircraft moved too far in between.
+///
+/// Returns `(lat, lon)` in degrees, or an error if the frames are inconsistent or straddle a
+/// latitude zone boundary, in which case the next pair should be tried.
+pub fn decode_cpr_airborne(
+    even: &CprFrame,
+    odd: &CprFrame,
+    odd_is_latest: bool,
+) -> Result<(f64, f64), &'static str> {
+    check_pair(even, odd)?;
+
+    let (mut rlat0, mut rlat1) = global_latitudes(even, odd, 360.0);
+    if rlat0 >= 270.0 {
+        rlat0 -= 360.0;
+    }
+    if rlat1 >= 270.0 {
+        rlat1 -= 360.0;
+    }
+
+    // Check to see that the latitude is in range: -90 .. +90
+    if !(-90.0..=90.0).contains(&rlat0) || !(-90.0..=90.0).contains(&rlat1) {
+        return Err("cpr: latitude out of range");
+    }
+
+    // Check that both are in the same latitude zone, or abort
+    if cpr_nl(rlat0) != cpr_nl(rlat1) {
+        return Err("cpr: frames crossed a latitude zone");
+    }
+
+    let rlat = if odd_is_latest { rlat1 } else { rlat0 };
+    let mut rlon = global_longitude(even, odd, rlat, odd_is_latest, false);
+
+    // Renormalize to -180 .. +180
+    rlon -= ((rlon + 180.0) / 360.0).floor() * 360.0;
+
+    Ok((rlat, rlon))
+}
+
+// Surface latitudes only cover a 90 degree range, pick the solution nearest the reference
+fn resolve_surface_latitude(rlat: f64, reflat: f64) -> f64 {
+    // -90, 0 and +90 all encode to zero
+    if rlat == 0.0 {
+        if reflat < -45.0 {
+            -90.0
+        } else if reflat > 45.0 {
+            90.0
+        } else {
+            0.0
+        }
+    } else if rlat - reflat > 45.0 {
+        rlat - 90.0
+    } else {
+        rlat
+    }
+}
+
+// cpr.c:decodeCPRsurface
+/// Globally unambiguous surface position from an even and an odd frame
+///
+/// Surface frames only encode position within a 90 degree quadrant, so a reference position
+/// `(reflat, reflon)` is needed to choose between the four possible solutions. The receiver
+/// location is good enough, as it only has to be within 45 degrees of the aircraft.
+///
+/// Returns `(lat, lon)` in degrees, see [`decode_cpr_airborne`] for the meaning of
+/// `odd_is_latest` and the errors.
+pub fn decode_cpr_surface(
+    reflat: f64,
+    reflon: f64,
+    even: &CprFrame,
+    odd: &CprFrame,
+    odd_is_latest: bool,
+) -> Result<(f64, f64), &'static str> {
+    check_pair(even, odd)?;
+
+    let (rlat0, rlat1) = global_latitudes(even, odd, 90.0);
+    let rlat0 = resolve_surface_latitude(rlat0, reflat);
+    let rlat1 = resolve_surface_latitude(rlat1, reflat);
+
+    // Check to see that the latitude is in range: -90 .. +90
+    if !(-90.0..=90.0).contains(&rlat0) || !(-90.0..=90.0).contains(&rlat1) {
+        return Err("cpr: latitude out of range");
+    }
+
+    // Check that both are in the same latitude zone, or abort
+    if cpr_nl(rlat0) != cpr_nl(rlat1) {
+        return Err("cpr: frames crossed a latitude zone");
+    }
+
+    let rlat = if odd_is_latest { rlat1 } else { rlat0 };
+    let mut rlon = global_longitude(even, odd, rlat, odd_is_latest, true);
+
+    // There are 4 possible longitudes, rlon + n * 90, pick the one closest to reflon
+    rlon += ((reflon - rlon + 45.0) / 90.0).floor() * 90.0;
+    // Renormalize to -180 .. +180
+    rlon -= ((rlon + 180.0) / 360.0).floor() * 360.0;
+
+    Ok((rlat, rlon))
+}
+
+// cpr.c:decodeCPRrelative
+/// Locally unambiguous position from a single frame near a reference position
+///
+/// The reference must be within half a zone of the aircraft: about 180 NM for airborne and
+/// 45 NM for surface positions. Use the receiver location for the first fix, then the last
+/// known position of the aircraft.
+///
+/// Returns `(lat, lon)` in degrees, or an error if the result is more than half a zone away
+/// from the reference.
+pub fn decode_cpr_relative(
+    reflat: f64,
+    reflon: f64,
+    cpr: &CprFrame,
+    surface: bool,
+) -> Result<(f64, f64), &'static str> {
+    let fractional_lat = f64::from(cpr.lat) / CPR_SCALE;
+    let fractional_lon = f64::from(cpr.lon) / CPR_SCALE;
+
+    let dlat = (if surface { 90.0 } else { 360.0 }) / (if cpr.odd { 59.0 } else { 60.0 });
+
+    // Compute the Latitude Index "j"
+    let j = (reflat / dlat).floor()
+        + (0.5 + cpr_mod_double(reflat, dlat) / dlat - fractional_lat).floor();
+    let mut rlat = dlat * (j + fractional_lat);
+    if rlat >= 270.0 {
+        rlat -= 360.0;
+    }
+
+    // Check to see that the latitude is in range: -90 .. +90
+    if !(-90.0..=90.0).contains(&rlat) {
+        return Err("cpr: latitude out of range");
+    }
+
+    // Check to see that answer is reasonable - ie no more than 1/2 cell away
+    if (rlat - reflat).abs() > dlat / 2.0 {
+        return Err("cpr: latitude too far from reference");
+    }
+
+    // Compute the Longitude Index "m"
+    let dlon = cpr_dlon(rlat, cpr.odd, surface);
+    let m = (reflon / dlon).floor()
+        + (0.5 + cpr_mod_double(reflon, dlon) / dlon - fractional_lon).floor();
+    let mut rlon = dlon * (m + fractional_lon);
+    if rlon > 180.0 {
+        rlon -= 360.0;
+    }
+
+    // Check to see that answer is reasonable - ie no more than 1/2 cell away
+    if (rlon - reflon).abs() > dlon / 2.0 {
+        return Err("cpr: longitude too far from reference");
+    }
+
+    Ok((rlat, rlon))
+}
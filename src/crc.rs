@@ -1,4 +1,7 @@
-//This module includes functionality translated from mode_s.c
+//This module includes functionality translated from mode_s.c and crc.c
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
 
 pub const CRC_TABLE: [u32; 256] = [
     0x0000_0000,
@@ -279,3 +282,137 @@ pub fn modes_checksum(message: &[u8], bits: usize) -> u32 {
 
     rem
 }
+
+// dump1090.h:MODES_MAX_BITERRORS
+pub const MODES_MAX_BITERRORS: usize = 2;
+
+// Syndrome mask for DF11, where the low 7 bits of the parity hold the interrogator identifier
+pub const DF11_SYNDROME_MASK: u32 = 0x00ff_ff80;
+
+// crc.h:errorinfo
+/// Bit errors that produce a given CRC syndrome
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ErrorInfo {
+    pub syndrome: u32,
+    pub errors: usize,
+    /// Bit positions, 0 is the first bit of the message. Only the first `errors` are valid.
+    pub bit: [usize; MODES_MAX_BITERRORS],
+}
+
+impl ErrorInfo {
+    #[must_use]
+    pub fn bits(&self) -> &[usize] {
+        &self.bit[..self.errors]
+    }
+}
+
+// 1 bit errors in DF11 (56 bits), 2 bit errors are ambiguous so we never try to fix them
+static ERROR_TABLE_SHORT: OnceLock<HashMap<u32, ErrorInfo>> = OnceLock::new();
+// 1 bit errors in DF17/DF18 (112 bits)
+static ERROR_TABLE_LONG_1: OnceLock<HashMap<u32, ErrorInfo>> = OnceLock::new();
+// 1 and 2 bit errors in DF17/DF18 (112 bits)
+static ERROR_TABLE_LONG_2: OnceLock<HashMap<u32, ErrorInfo>> = OnceLock::new();
+
+// crc.c:modesChecksumDiagnose
+/// Find the bit errors, up to `nfix`, that would produce `syndrome` in a message of `bits` bits
+///
+/// A zero syndrome returns an `ErrorInfo` with no errors. DF11 syndromes must be masked with
+/// `DF11_SYNDROME_MASK` first, and are never corrected for more than one bit.
+#[must_use]
+pub fn modes_checksum_diagnose(syndrome: u32, bits: usize, nfix: usize) -> Option<ErrorInfo> {
+    if syndrome == 0 {
+        return Some(ErrorInfo { syndrome, errors: 0, bit: [0; MODES_MAX_BITERRORS] });
+    }
+
+    let table = match (bits, nfix) {
+        (_, 0) => return None,
+        (56, _) => {
+            ERROR_TABLE_SHORT.get_or_init(|| prepare_error_table(56, DF11_SYNDROME_MASK, 1, 1))
+        }
+        (112, 1) => ERROR_TABLE_LONG_1.get_or_init(|| prepare_error_table(112, 0x00ff_ffff, 1, 1)),
+        // Detect out to 4 bit errors, so that we don't miscorrect a 4 bit error as a 2 bit one
+        (112, _) => ERROR_TABLE_LONG_2.get_or_init(|| prepare_error_table(112, 0x00ff_ffff, 2, 4)),
+        _ => return None,
+    };
+
+    table.get(&syndrome).copied()
+}
+
+// crc.c:modesChecksumFix
+pub fn modes_checksum_fix(msg: &mut [u8], info: &ErrorInfo) {
+    for &bit in info.bits() {
+        msg[bit / 8] ^= 1 << (7 - (bit % 8));
+    }
+}
+
+// crc.c:prepareErrorTable
+// Table of all syndromes of up to `max_correct` bit errors, with the DF field excluded. Syndromes
+// that can also be produced by a different pattern of up to `max_detect` errors are ambiguous and
+// left out.
+fn prepare_error_table(
+    bits: usize,
+    syndrome_mask: u32,
+    max_correct: usize,
+    max_detect: usize,
+) -> HashMap<u32, ErrorInfo> {
+    let bytes = bits / 8;
+    let single: Vec<(usize, u32)> = (5..bits)
+        .map(|i| {
+            let mut msg = [0_u8; 14];
+            msg[i / 8] = 1 << (7 - (i % 8));
+            (i, modes_checksum(&msg[..bytes], bits) & syndrome_mask)
+        })
+        .collect();
+
+    let mut table: HashMap<u32, ErrorInfo> = HashMap::new();
+    let mut ambiguous: HashSet<u32> = HashSet::new();
+
+    for errors in 1..=max_correct {
+        for_each_combination(&single, errors, 0, 0, &mut vec![], &mut |syndrome, positions| {
+            if syndrome == 0 {
+                // undetectable, e.g. an error in the DF11 IID field
+                return;
+            }
+            let mut bit = [0; MODES_MAX_BITERRORS];
+            bit[..positions.len()].copy_from_slice(positions);
+            let info = ErrorInfo { syndrome, errors: positions.len(), bit };
+            if table.insert(syndrome, info).is_some() {
+                ambiguous.insert(syndrome);
+            }
+        });
+    }
+
+    for errors in max_correct + 1..=max_detect {
+        for_each_combination(&single, errors, 0, 0, &mut vec![], &mut |syndrome, _| {
+            if table.contains_key(&syndrome) {
+                ambiguous.insert(syndrome);
+            }
+        });
+    }
+
+    for syndrome in ambiguous {
+        table.remove(&syndrome);
+    }
+
+    table
+}
+
+fn for_each_combination(
+    single: &[(usize, u32)],
+    remaining: usize,
+    start: usize,
+    syndrome: u32,
+    positions: &mut Vec<usize>,
+    f: &mut dyn FnMut(u32, &[usize]),
+) {
+    if remaining == 0 {
+        f(syndrome, positions);
+        return;
+    }
+
+    for (i, &(bit, bit_syndrome)) in single.iter().enumerate().skip(start) {
+        positions.push(bit);
+        for_each_combination(single, remaining - 1, i + 1, syndrome ^ bit_syndrome, positions, f);
+        positions.pop();
+    }
+}
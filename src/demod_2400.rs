@@ -1,6 +1,18 @@
 // This module includes functionality translated from demod_2400.c
 
-use crate::{mode_s, MagnitudeBuffer, MODES_LONG_MSG_BYTES};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use num_complex::Complex;
+
+use crate::icao_filter::{IcaoFilter, MODES_ICAO_FILTER_TTL};
+use crate::{
+    mode_s, utils, MagnitudeBuffer, ModesMessage, MODES_LONG_MSG_BYTES, MODES_MAG_BUF_SAMPLES,
+    TRAILING_SAMPLES,
+};
+
+// Used by the free `demodulate2400`, which shares a single filter between all callers
+static DEFAULT_DEMODULATOR: Mutex<Option<Demodulator>> = Mutex::new(None);
 
 #[derive(Clone, Copy, Debug)]
 enum Phase {
@@ -77,87 +89,284 @@ impl Phase {
     }
 }
 
-pub fn demodulate2400(mag: &MagnitudeBuffer) -> Result<Vec<[u8; 14]>, &'static str> {
-    let mut results = vec![];
+/// Settings for a [`Demodulator`]
+#[derive(Clone, Copy, Debug)]
+pub struct DemodulatorConfig {
+    /// How long an address stays in the ICAO filter after it was last heard in a DF11/DF17/DF18
+    ///
+    /// Addresses are forgotten between one and two of these intervals after they were added.
+    pub icao_filter_ttl: Duration,
+    /// Number of bit errors to repair in DF11/DF17/DF18 using the CRC, 0 to disable
+    ///
+    /// Up to `MODES_MAX_BITERRORS` (2) in DF17/DF18. DF11 is never corrected for more than one
+    /// bit, as two bit errors are ambiguous there.
+    pub nfix_crc: usize,
+}
+
+impl Default for DemodulatorConfig {
+    fn default() -> Self {
+        Self { icao_filter_ttl: MODES_ICAO_FILTER_TTL, nfix_crc: 1 }
+    }
+}
+
+/// Running counters kept by a [`Demodulator`], matching the `demod_*` fields of dump1090's stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DemodulatorStats {
+    /// Preambles that passed the signal and quiet bit checks
+    pub preambles: u64,
+    /// Candidates rejected for a bad CRC or unknown message type
+    pub rejected_bad: u64,
+    /// Candidates rejected because the address/parity did not match a known aircraft
+    pub rejected_unknown_icao: u64,
+    /// Messages returned to the caller
+    pub accepted: u64,
+    /// Accepted messages that had one or more bits repaired
+    pub corrected: u64,
+}
+
+/// Mode S demodulator for 2.4MHz magnitude data
+///
+/// Each instance owns its ICAO filter and statistics, so several receivers in one process don't
+/// share or contend on state.
+#[derive(Clone, Debug)]
+pub struct Demodulator {
+    config: DemodulatorConfig,
+    icao_filter: IcaoFilter,
+    stats: DemodulatorStats,
+    // Allocated on the first call to `demodulate_samples`
+    stream: Option<Box<MagnitudeBuffer>>,
+}
 
-    let data = &mag.data;
+impl Default for Demodulator {
+    fn default() -> Self {
+        Self::new(DemodulatorConfig::default())
+    }
+}
 
-    let mut skip_count: usize = 0;
-    'jloop: for j in 0..mag.length {
-        if skip_count > 0 {
-            skip_count -= 1;
-            continue 'jloop;
+impl Demodulator {
+    #[must_use]
+    pub fn new(config: DemodulatorConfig) -> Self {
+        Self {
+            config,
+            icao_filter: IcaoFilter::new(config.icao_filter_ttl),
+            stats: DemodulatorStats::default(),
+            stream: None,
         }
+    }
 
-        if let Some((high, base_signal, base_noise)) = check_preamble(&data[j..j + 14]) {
-            // Check for enough signal
-            if base_signal * 2 < 3 * base_noise {
-                // about 3.5dB SNR
-                continue 'jloop;
+    #[must_use]
+    pub fn config(&self) -> &DemodulatorConfig {
+        &self.config
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> &DemodulatorStats {
+        &self.stats
+    }
+
+    /// Reset the statistics, returning the values collected since the last reset
+    pub fn reset_stats(&mut self) -> DemodulatorStats {
+        std::mem::take(&mut self.stats)
+    }
+
+    /// 12MHz timestamp that the next sample passed to `demodulate_samples` will have, in the
+    /// timebase of `ModesMessage::timestamp_12mhz`
+    #[must_use]
+    pub fn next_sample_timestamp_12mhz(&self) -> u64 {
+        match &self.stream {
+            Some(stream) => {
+                stream.first_sample_timestamp_12mhz + (TRAILING_SAMPLES + stream.length) as u64 * 5
             }
+            None => TRAILING_SAMPLES as u64 * 5,
+        }
+    }
+
+    /// Demodulate the next samples of a continuous stream
+    ///
+    /// Unlike `demodulate2400`, the trailing samples and the 12MHz timestamp are kept between
+    /// calls, so messages that cross the boundary between two reads are not lost. Input of any
+    /// length is accepted.
+    pub fn demodulate_samples(
+        &mut self,
+        samples: &[Complex<i16>],
+    ) -> Result<Vec<ModesMessage>, &'static str> {
+        let mut results = vec![];
+
+        let mut stream = self.stream.take().unwrap_or_default();
+        for chunk in samples.chunks(MODES_MAG_BUF_SAMPLES) {
+            stream.next_block();
+            utils::to_mag_into(chunk, &mut stream);
+            match self.demodulate2400(&stream) {
+                Ok(messages) => results.extend(messages),
+                Err(e) => {
+                    self.stream = Some(stream);
+                    return Err(e);
+                }
+            }
+        }
+        self.stream = Some(stream);
+
+        Ok(results)
+    }
 
-            // Check that the "quiet" bits 6,7,15,16,17 are actually quiet
-            if i32::from(data[j + 5]) >= high
-                || i32::from(data[j + 6]) >= high
-                || i32::from(data[j + 7]) >= high
-                || i32::from(data[j + 8]) >= high
-                || i32::from(data[j + 14]) >= high
-                || i32::from(data[j + 15]) >= high
-                || i32::from(data[j + 16]) >= high
-                || i32::from(data[j + 17]) >= high
-                || i32::from(data[j + 18]) >= high
-            {
+    /// Forget the samples kept by `demodulate_samples`, for example after the source dropped
+    /// samples. The timestamp keeps running.
+    pub fn reset_stream(&mut self) {
+        if let Some(stream) = &mut self.stream {
+            stream.next_block();
+            stream.data.fill(0);
+        }
+    }
+
+    pub fn demodulate2400(
+        &mut self,
+        mag: &MagnitudeBuffer,
+    ) -> Result<Vec<ModesMessage>, &'static str> {
+        let mut results = vec![];
+
+        self.icao_filter.expire();
+
+        // a short last block of a stream leaves older samples after the `length` new ones
+        let data = &mag.data[..TRAILING_SAMPLES + mag.length];
+
+        let mut skip_count: usize = 0;
+        'jloop: for j in 0..mag.length {
+            if skip_count > 0 {
+                skip_count -= 1;
                 continue 'jloop;
             }
 
-            // Try all phases
-            let mut bestmsg: [u8; MODES_LONG_MSG_BYTES] = [0_u8; MODES_LONG_MSG_BYTES];
-            let mut bestscore: i32 = -2;
-
-            let mut msg: [u8; MODES_LONG_MSG_BYTES] = [0_u8; MODES_LONG_MSG_BYTES];
-            for try_phase in 4..9 {
-                let mut slice_loc: usize = j + 19 + (try_phase / 5);
-                let mut phase = Phase::from(try_phase);
-
-                for msg in msg.iter_mut().take(MODES_LONG_MSG_BYTES) {
-                    let slice_this_byte: &[u16] = &data[slice_loc..];
-
-                    let starting_phase = phase;
-                    let mut the_byte = 0x00;
-                    let mut index = 0;
-                    // for each phase-bit
-                    for i in 0..8 {
-                        // find if phase distance denotes a high bit
-                        if phase.calculate_bit(&slice_this_byte[index..index + 4]) > 0 {
-                            the_byte |= 1 << (7 - i);
+            if let Some((high, base_signal, base_noise)) = check_preamble(&data[j..j + 14]) {
+                // Check for enough signal
+                if base_signal * 2 < 3 * base_noise {
+                    // about 3.5dB SNR
+                    continue 'jloop;
+                }
+
+                // Check that the "quiet" bits 6,7,15,16,17 are actually quiet
+                if i32::from(data[j + 5]) >= high
+                    || i32::from(data[j + 6]) >= high
+                    || i32::from(data[j + 7]) >= high
+                    || i32::from(data[j + 8]) >= high
+                    || i32::from(data[j + 14]) >= high
+                    || i32::from(data[j + 15]) >= high
+                    || i32::from(data[j + 16]) >= high
+                    || i32::from(data[j + 17]) >= high
+                    || i32::from(data[j + 18]) >= high
+                {
+                    continue 'jloop;
+                }
+
+                self.stats.preambles += 1;
+
+                // Try all phases
+                let mut bestmsg: [u8; MODES_LONG_MSG_BYTES] = [0_u8; MODES_LONG_MSG_BYTES];
+                let mut bestscore: i32 = -2;
+                let mut bestphase: usize = 0;
+
+                let mut msg: [u8; MODES_LONG_MSG_BYTES] = [0_u8; MODES_LONG_MSG_BYTES];
+                for try_phase in 4..9 {
+                    let mut slice_loc: usize = j + 19 + (try_phase / 5);
+                    let mut phase = Phase::from(try_phase);
+
+                    for msg in msg.iter_mut().take(MODES_LONG_MSG_BYTES) {
+                        let slice_this_byte: &[u16] = &data[slice_loc..];
+
+                        let starting_phase = phase;
+                        let mut the_byte = 0x00;
+                        let mut index = 0;
+                        // for each phase-bit
+                        for i in 0..8 {
+                            // find if phase distance denotes a high bit
+                            if phase.calculate_bit(&slice_this_byte[index..index + 4]) > 0 {
+                                the_byte |= 1 << (7 - i);
+                            }
+                            // increment to next phase, increase index
+                            index = phase.increment_index(index);
+                            phase = phase.next();
                         }
-                        // increment to next phase, increase index
-                        index = phase.increment_index(index);
-                        phase = phase.next();
+                        // save bytes and move the next starting phase
+                        *msg = the_byte;
+                        slice_loc += index;
+                        phase = starting_phase.next_start();
+                    }
+
+                    let score =
+                        mode_s::score_modes_message(&msg, &self.icao_filter, self.config.nfix_crc);
+
+                    if score > bestscore {
+                        bestmsg.clone_from_slice(&msg);
+                        bestscore = score;
+                        bestphase = try_phase;
+                    }
+                }
+
+                // Do we have a candidate?
+                if bestscore < 0 {
+                    if bestscore == -1 {
+                        self.stats.rejected_unknown_icao += 1;
+                    } else {
+                        self.stats.rejected_bad += 1;
                     }
-                    // save bytes and move the next starting phase
-                    *msg = the_byte;
-                    slice_loc += index;
-                    phase = starting_phase.next_start();
+                    continue 'jloop;
                 }
 
-                let score = mode_s::score_modes_message(&msg);
+                self.stats.accepted += 1;
+
+                let msglen = mode_s::modes_message_len_by_type(mode_s::getbits(&bestmsg, 1, 5));
 
-                if score > bestscore {
-                    bestmsg.clone_from_slice(&msg);
-                    bestscore = score;
+                // don't leak the bits demodulated after the end of a short message
+                bestmsg[msglen / 8..].fill(0);
+
+                let corrected_bits =
+                    mode_s::correct_modes_message(&mut bestmsg[..msglen / 8], self.config.nfix_crc);
+                if !corrected_bits.is_empty() {
+                    self.stats.corrected += 1;
                 }
-            }
 
-            // Do we have a candidate?
-            if bestscore < 0 {
-                continue 'jloop;
+                // measure signal power
+                let signal_len = msglen * 12 / 5;
+                let scaled_signal_power: u64 = data[j + 19..j + 19 + signal_len]
+                    .iter()
+                    .map(|&mag| u64::from(mag) * u64::from(mag))
+                    .sum();
+                let signal_power = scaled_signal_power as f64 / 65535.0 / 65535.0;
+                let signal_level = signal_power / signal_len as f64;
+
+                // corrected messages are not trusted enough to add new addresses
+                if corrected_bits.is_empty() {
+                    if let Some(addr) = mode_s::verified_address(&bestmsg[..msglen / 8]) {
+                        self.icao_filter.add(addr);
+                    }
+                }
+
+                results.push(ModesMessage {
+                    data: bestmsg,
+                    len: msglen / 8,
+                    // compute message receive time as block-start-time + difference in the 12MHz clock
+                    timestamp_12mhz: mag.first_sample_timestamp_12mhz
+                        + (j * 5) as u64
+                        + ((8 + 56) * 12) as u64
+                        + bestphase as u64,
+                    rssi: 10.0 * signal_level.log10(),
+                    score: bestscore,
+                    phase: bestphase as u8,
+                    corrected_bits,
+                });
             }
-            results.push(bestmsg);
         }
+
+        Ok(results)
     }
+}
 
-    Ok(results)
+/// Demodulate using a process-wide [`Demodulator`]
+///
+/// Kept for backwards compatibility, all callers share one ICAO filter. Prefer owning a
+/// [`Demodulator`].
+pub fn demodulate2400(mag: &MagnitudeBuffer) -> Result<Vec<ModesMessage>, &'static str> {
+    let mut demodulator = DEFAULT_DEMODULATOR.lock().map_err(|_| "demodulator lock poisoned")?;
+    demodulator.get_or_insert_with(Demodulator::default).demodulate2400(mag)
 }
 
 fn check_preamble(preamble: &[u16]) -> Option<(i32, u32, u32)> {
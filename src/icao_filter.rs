@@ -1,11 +1,22 @@
 //This module includes functionality translated from icao_filter.c
 
-use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const ICAO_FILTER_SIZE: u32 = 4096;
 
-static ICAO_FILTER_A: Mutex<[u32; 4096]> = Mutex::new([0; 4096]);
-static ICAO_FILTER_B: Mutex<[u32; 4096]> = Mutex::new([0; 4096]);
+// dump1090.h:MODES_ICAO_FILTER_TTL
+pub const MODES_ICAO_FILTER_TTL: Duration = Duration::from_secs(60);
+
+// The original uses two static tables and a pointer to the active one, new addresses are only
+// added to the active table and lookups check both
+#[derive(Clone, Debug)]
+pub struct IcaoFilter {
+    icao_filter_a: [u32; 4096],
+    icao_filter_b: [u32; 4096],
+    active_is_a: bool,
+    next_flip: Option<Instant>,
+    ttl: Duration,
+}
 
 pub fn icao_hash(a32: u32) -> u32 // icao_filter.c:38
 {
@@ -33,37 +44,83 @@ pub fn icao_hash(a32: u32) -> u32 // icao_filter.c:38
     (hash as u32) & (ICAO_FILTER_SIZE - 1)
 }
 
-// The original function uses a integer return value, but it's used as a boolean
-pub fn icao_filter_test(addr: u32) -> bool // icao_filter.c:96
-{
-    let mut h: u32 = icao_hash(addr);
-    let h0: u32 = icao_hash(addr);
+impl IcaoFilter {
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self // icao_filter.c:56
+    {
+        Self {
+            icao_filter_a: [0; 4096],
+            icao_filter_b: [0; 4096],
+            active_is_a: true,
+            next_flip: None,
+            ttl,
+        }
+    }
 
-    if let (Ok(icao_filter_a), Ok(icao_filter_b)) = (ICAO_FILTER_A.lock(), ICAO_FILTER_B.lock()) {
-        'loop_a: while (icao_filter_a[h as usize] != 0) && (icao_filter_a[h as usize] != addr) {
+    // The original function uses a integer return value, but it's used as a boolean
+    #[must_use]
+    pub fn test(&self, addr: u32) -> bool // icao_filter.c:96
+    {
+        // 0 marks an empty slot, so it would match any address that isn't in the filter
+        addr != 0 && (lookup(&self.icao_filter_a, addr) || lookup(&self.icao_filter_b, addr))
+    }
+
+    pub fn add(&mut self, addr: u32) // icao_filter.c:62
+    {
+        let icao_filter =
+            if self.active_is_a { &mut self.icao_filter_a } else { &mut self.icao_filter_b };
+
+        let h0: u32 = icao_hash(addr);
+        let mut h: u32 = h0;
+
+        while (icao_filter[h as usize] != 0) && (icao_filter[h as usize] != addr) {
             h = (h + 1) & (ICAO_FILTER_SIZE - 1);
             if h == h0 {
-                break 'loop_a;
+                // table is full, drop the address
+                return;
             }
         }
 
-        if icao_filter_a[h as usize] == addr {
-            return true;
+        if icao_filter[h as usize] == 0 {
+            icao_filter[h as usize] = addr;
         }
+    }
 
-        h = h0;
-
-        'loop_b: while (icao_filter_b[h as usize] != 0) && (icao_filter_b[h as usize] != addr) {
-            h = (h + 1) & (ICAO_FILTER_SIZE - 1);
-            if h == h0 {
-                break 'loop_b;
+    // Swap the active table every ttl, clearing the one that becomes active, so that an address
+    // is forgotten between one and two ttls after it was last added
+    pub fn expire(&mut self) // icao_filter.c:121
+    {
+        let now = Instant::now();
+
+        if self.next_flip.map_or(true, |next_flip| now >= next_flip) {
+            if self.active_is_a {
+                self.icao_filter_b.fill(0);
+            } else {
+                self.icao_filter_a.fill(0);
             }
+            self.active_is_a = !self.active_is_a;
+
+            self.next_flip = Some(now + self.ttl);
         }
+    }
+}
+
+impl Default for IcaoFilter {
+    fn default() -> Self {
+        Self::new(MODES_ICAO_FILTER_TTL)
+    }
+}
+
+fn lookup(icao_filter: &[u32; 4096], addr: u32) -> bool {
+    let h0: u32 = icao_hash(addr);
+    let mut h: u32 = h0;
 
-        if icao_filter_b[h as usize] == addr {
-            return true;
+    while (icao_filter[h as usize] != 0) && (icao_filter[h as usize] != addr) {
+        h = (h + 1) & (ICAO_FILTER_SIZE - 1);
+        if h == h0 {
+            break;
         }
     }
 
-    false
+    icao_filter[h as usize] == addr
 }
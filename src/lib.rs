@@ -9,7 +9,16 @@ timing, and things like that might give results that are not quite identical.
 #![allow(non_camel_case_types)]
 
 // public
+pub mod convert;
+pub mod cpr;
 pub mod demod_2400;
+pub mod mode_s;
+pub mod net_io;
+pub mod rtl_tcp;
+pub mod sigmf;
+pub mod source;
+pub mod synth;
+pub mod tracker;
 
 // public(crate)
 pub mod utils;
@@ -17,7 +26,6 @@ pub mod utils;
 // private
 mod crc;
 mod icao_filter;
-mod mode_s;
 
 pub const MODES_MAG_BUF_SAMPLES: usize = 131_072;
 
@@ -25,12 +33,66 @@ const TRAILING_SAMPLES: usize = 326;
 const MODES_LONG_MSG_BYTES: usize = 14;
 const MODES_SHORT_MSG_BYTES: usize = 7;
 
+// dump1090.h:modesMessage
+/// A demodulated Mode S message along with receive metadata
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModesMessage {
+    /// Message bytes, only the first `len` are valid and the rest are zeroed
+    pub data: [u8; MODES_LONG_MSG_BYTES],
+    /// Message length in bytes, 7 for short (56-bit) and 14 for long (112-bit) messages
+    pub len: usize,
+    /// Receive time in ticks of a 12MHz clock, same timebase as `first_sample_timestamp_12mhz`
+    pub timestamp_12mhz: u64,
+    /// Mean signal power over the message bits in dBFS
+    pub rssi: f64,
+    /// Score from `score_modes_message`, higher is more trustworthy
+    pub score: i32,
+    /// Sample phase (4..=8, in 1/5ths of a sample) the message was decoded at
+    pub phase: u8,
+    /// Bit positions (0 is the first bit of the message) that were repaired using the CRC
+    pub corrected_bits: Vec<usize>,
+}
+
+impl ModesMessage {
+    /// A message demodulated elsewhere, such as by another receiver on the network
+    ///
+    /// The length has to match the downlink format. No CRC checking is done, use a
+    /// [`mode_s::MessageValidator`] unless the sender is trusted to have done this.
+    pub fn from_bytes(bytes: &[u8], timestamp_12mhz: u64, rssi: f64) -> Result<Self, &'static str> {
+        let Some(&first) = bytes.first() else {
+            return Err("empty message");
+        };
+        let len = mode_s::modes_message_len_by_type(usize::from(first >> 3)) / 8;
+        if bytes.len() != len {
+            return Err("message length doesn't match its downlink format");
+        }
+
+        let mut data = [0; MODES_LONG_MSG_BYTES];
+        data[..len].copy_from_slice(bytes);
+        Ok(Self { data, len, timestamp_12mhz, rssi, score: 0, phase: 0, corrected_bits: vec![] })
+    }
+
+    /// The valid message bytes
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for ModesMessage {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
 // dump1090.h:252
 #[derive(Copy, Clone, Debug)]
 pub struct MagnitudeBuffer {
+    /// `TRAILING_SAMPLES` carried over from the previous block, followed by `length` new samples
     pub data: [u16; TRAILING_SAMPLES + MODES_MAG_BUF_SAMPLES],
     pub length: usize,
-    pub first_sample_timestamp_12mhz: usize,
+    /// Time of `data[0]` in ticks of a 12MHz clock (5 ticks per sample at 2.4MHz)
+    pub first_sample_timestamp_12mhz: u64,
 }
 
 impl Default for MagnitudeBuffer {
@@ -48,4 +110,16 @@ impl MagnitudeBuffer {
         self.data[TRAILING_SAMPLES + self.length] = x;
         self.length += 1;
     }
+
+    /// Start the next block of a continuous stream
+    ///
+    /// The last `TRAILING_SAMPLES` of this block are kept at the front of the buffer, so that a
+    /// message crossing the boundary can still be demodulated, and the timestamp is advanced past
+    /// the samples already processed.
+    pub fn next_block(&mut self) {
+        let length = self.length;
+        self.data.copy_within(length..length + TRAILING_SAMPLES, 0);
+        self.first_sample_timestamp_12mhz += length as u64 * 5;
+        self.length = 0;
+    }
 }
@@ -0,0 +1,184 @@
+// This module includes functionality translated from mode_s.c and mode_ac.c
+
+use super::decode_id13_field;
+
+const FEET_PER_METER: f64 = 3.280_84;
+
+/// Unit an altitude was reported in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AltitudeUnit {
+    Feet,
+    Meters,
+}
+
+/// A decoded altitude
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Altitude {
+    pub value: i32,
+    pub unit: AltitudeUnit,
+}
+
+impl Altitude {
+    #[must_use]
+    pub fn feet(value: i32) -> Self {
+        Self { value, unit: AltitudeUnit::Feet }
+    }
+
+    #[must_use]
+    pub fn meters(value: i32) -> Self {
+        Self { value, unit: AltitudeUnit::Meters }
+    }
+
+    /// Altitude converted to feet, rounded to the nearest foot
+    #[must_use]
+    pub fn in_feet(self) -> i32 {
+        match self.unit {
+            AltitudeUnit::Feet => self.value,
+            AltitudeUnit::Meters => (f64::from(self.value) * FEET_PER_METER).round() as i32,
+        }
+    }
+}
+
+impl std::fmt::Display for Altitude {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.unit {
+            AltitudeUnit::Feet => write!(f, "{} ft", self.value),
+            AltitudeUnit::Meters => write!(f, "{} m", self.value),
+        }
+    }
+}
+
+// mode_ac.c:ModeAToModeC
+/// Convert a Gillham coded altitude, in the hex Gillham layout of [`decode_id13_field`], to
+/// hundreds of feet
+///
+/// Returns `None` for codes that can't be a Mode C altitude.
+#[must_use]
+pub fn mode_a_to_mode_c(mode_a: u16) -> Option<i32> {
+    // D1 set is illegal, D2 set is > 62700ft which is unlikely
+    // C1,,C4 cannot be zero
+    if (mode_a & 0x888b) != 0 || (mode_a & 0x00f0) == 0 {
+        return None;
+    }
+
+    let mut one_hundreds: i32 = 0;
+    if mode_a & 0x0010 != 0 {
+        one_hundreds ^= 0x007; // C1
+    }
+    if mode_a & 0x0020 != 0 {
+        one_hundreds ^= 0x003; // C2
+    }
+    if mode_a & 0x0040 != 0 {
+        one_hundreds ^= 0x001; // C4
+    }
+
+    // Remove 7s from one_hundreds (make 7->5, and 5->7)
+    if (one_hundreds & 5) == 5 {
+        one_hundreds ^= 2;
+    }
+
+    // Check for invalid codes, only 1 to 5 are valid
+    if one_hundreds > 5 {
+        return None;
+    }
+
+    let mut five_hundreds: i32 = 0;
+    // D1 is never used for altitude
+    for (bit, value) in [
+        (0x0002, 0x0ff), // D2
+        (0x0004, 0x07f), // D4
+        (0x1000, 0x03f), // A1
+        (0x2000, 0x01f), // A2
+        (0x4000, 0x00f), // A4
+        (0x0100, 0x007), // B1
+        (0x0200, 0x003), // B2
+        (0x0400, 0x001), // B4
+    ] {
+        if mode_a & bit != 0 {
+            five_hundreds ^= value;
+        }
+    }
+
+    // Correct order of one_hundreds
+    if five_hundreds & 1 != 0 {
+        one_hundreds = 6 - one_hundreds;
+    }
+
+    Some((five_hundreds * 5) + one_hundreds - 13)
+}
+
+// Gillham coded altitude in feet, codes below -1200ft are invalid
+fn decode_gillham(id13_field: usize) -> Option<Altitude> {
+    let n = mode_a_to_mode_c(decode_id13_field(id13_field))?;
+    if n < -12 {
+        return None;
+    }
+    Some(Altitude::feet(100 * n))
+}
+
+// mode_s.c:decodeAC13Field
+/// Decode the 13 bit altitude code of DF0/4/16/20
+///
+/// Returns `None` if the altitude is not available or the code is invalid.
+///
+/// With the M bit set the remaining 12 bits are returned as meters. The metric encoding is
+/// reserved and not further specified, so such altitudes should be treated with suspicion.
+#[must_use]
+pub fn decode_ac13_field(ac13_field: u16) -> Option<Altitude> {
+    let ac13_field = usize::from(ac13_field & 0x1fff);
+    let m_bit = ac13_field & 0x0040 != 0; // set = meters, clear = feet
+    let q_bit = ac13_field & 0x0010 != 0; // set = 25 ft encoding, clear = Gillham Mode C encoding
+
+    if ac13_field == 0 {
+        return None;
+    }
+
+    if m_bit {
+        // N is the 12 bit integer resulting from the removal of bit M
+        let n = ((ac13_field & 0x1f80) >> 1) | (ac13_field & 0x003f);
+        return Some(Altitude::meters(n as i32));
+    }
+
+    if q_bit {
+        // N is the 11 bit integer resulting from the removal of bit Q and M
+        let n = ((ac13_field & 0x1f80) >> 2) | ((ac13_field & 0x0020) >> 1) | (ac13_field & 0x000f);
+        Some(Altitude::feet((n as i32 * 25) - 1000))
+    } else {
+        // N is an 11 bit Gillham coded altitude
+        decode_gillham(ac13_field)
+    }
+}
+
+// mode_s.c:decodeAC12Field
+/// Decode the 12 bit altitude code of an extended squitter barometric airborne position
+///
+/// Returns `None` if the altitude is not available or the code is invalid.
+#[must_use]
+pub fn decode_ac12_field(ac12_field: u16) -> Option<Altitude> {
+    let ac12_field = usize::from(ac12_field & 0x0fff);
+    let q_bit = ac12_field & 0x0010 != 0; // Bit 48 = Q
+
+    if ac12_field == 0 {
+        return None;
+    }
+
+    if q_bit {
+        // N is the 11 bit integer resulting from the removal of bit Q at bit 4
+        let n = ((ac12_field & 0x0fe0) >> 1) | (ac12_field & 0x000f);
+        Some(Altitude::feet((n as i32 * 25) - 1000))
+    } else {
+        // Make N a 13 bit Gillham coded altitude by inserting M=0 at bit 6
+        decode_gillham(((ac12_field & 0x0fc0) << 1) | (ac12_field & 0x003f))
+    }
+}
+
+/// Decode the 12 bit GNSS height of an extended squitter GNSS airborne position (TC20-22)
+///
+/// Returns `None` if the height is not available.
+#[must_use]
+pub fn decode_gnss_height(ac12_field: u16) -> Option<Altitude> {
+    match ac12_field & 0x0fff {
+        0 => None,
+        n => Some(Altitude::meters(i32::from(n))),
+    }
+}
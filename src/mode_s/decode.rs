@@ -0,0 +1,554 @@
+// This module includes functionality translated from mode_s.c:decodeModesMessage
+
+use super::altitude::{decode_ac12_field, decode_ac13_field, decode_gnss_height, Altitude};
+use super::velocity::{decode_airborne_velocity, AirborneVelocity};
+use super::{decode_id13_field, getbits, modes_message_len_by_type};
+use crate::crc::modes_checksum;
+use crate::ModesMessage;
+
+// mode_s.c:ais_charset
+const AIS_CHARSET: &[u8; 64] = b"?ABCDEFGHIJKLMNOPQRSTUVWXYZ????? ???????????????0123456789??????";
+
+/// A decoded Mode S downlink message
+///
+/// For the address/parity formats (DF0/4/5/16/20/21/24) the `address` is recovered from the
+/// parity field, so it is only meaningful if the message was accepted by the demodulator.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DownlinkFormat {
+    /// DF0: Short air-air surveillance (ACAS)
+    ShortAirAirSurveillance {
+        vs: VerticalStatus,
+        cc: u8,
+        sl: u8,
+        ri: u8,
+        /// `None` if not available or invalid
+        altitude: Option<Altitude>,
+        address: u32,
+    },
+    /// DF4: Surveillance, altitude reply
+    SurveillanceAltitudeReply {
+        fs: FlightStatus,
+        dr: u8,
+        um: u8,
+        /// `None` if not available or invalid
+        altitude: Option<Altitude>,
+        address: u32,
+    },
+    /// DF5: Surveillance, identity reply
+    SurveillanceIdentityReply { fs: FlightStatus, dr: u8, um: u8, squawk: Squawk, address: u32 },
+    /// DF11: All-call reply
+    AllCallReply {
+        ca: Capability,
+        address: u32,
+        /// Interrogator identifier, the remainder of the parity
+        iid: u8,
+    },
+    /// DF16: Long air-air surveillance (ACAS)
+    LongAirAirSurveillance {
+        vs: VerticalStatus,
+        sl: u8,
+        ri: u8,
+        /// `None` if not available or invalid
+        altitude: Option<Altitude>,
+        mv: [u8; 7],
+        address: u32,
+    },
+    /// DF17: Extended squitter
+    ExtendedSquitter { ca: Capability, address: u32, me: ExtendedSquitter },
+    /// DF18: Extended squitter/non-transponder
+    ///
+    /// The ME field is only ADS-B formatted for control fields 0, 1, 2, 5 and 6, it is `None` for
+    /// the rest.
+    ExtendedSquitterNonTransponder { cf: u8, address: u32, me: Option<ExtendedSquitter> },
+    /// DF19: Military extended squitter
+    MilitaryExtendedSquitter { af: u8, data: [u8; 13] },
+    /// DF20: Comm-B, altitude reply
+    CommBAltitudeReply {
+        fs: FlightStatus,
+        dr: u8,
+        um: u8,
+        /// `None` if not available or invalid
+        altitude: Option<Altitude>,
+        mb: [u8; 7],
+        address: u32,
+    },
+    /// DF21: Comm-B, identity reply
+    CommBIdentityReply {
+        fs: FlightStatus,
+        dr: u8,
+        um: u8,
+        squawk: Squawk,
+        mb: [u8; 7],
+        address: u32,
+    },
+    /// DF24: Comm-D extended length message
+    CommD { nd: u8, md: [u8; 10], address: u32 },
+    /// Any other downlink format
+    Unknown { df: u8 },
+}
+
+impl DownlinkFormat {
+    /// Downlink format number, 24 for all Comm-D messages
+    #[must_use]
+    pub fn df(&self) -> u8 {
+        match self {
+            Self::ShortAirAirSurveillance { .. } => 0,
+            Self::SurveillanceAltitudeReply { .. } => 4,
+            Self::SurveillanceIdentityReply { .. } => 5,
+            Self::AllCallReply { .. } => 11,
+            Self::LongAirAirSurveillance { .. } => 16,
+            Self::ExtendedSquitter { .. } => 17,
+            Self::ExtendedSquitterNonTransponder { .. } => 18,
+            Self::MilitaryExtendedSquitter { .. } => 19,
+            Self::CommBAltitudeReply { .. } => 20,
+            Self::CommBIdentityReply { .. } => 21,
+            Self::CommD { .. } => 24,
+            Self::Unknown { df } => *df,
+        }
+    }
+
+    /// 24 bit ICAO address of the aircraft
+    #[must_use]
+    pub fn address(&self) -> Option<u32> {
+        match self {
+            Self::ShortAirAirSurveillance { address, .. }
+            | Self::SurveillanceAltitudeReply { address, .. }
+            | Self::SurveillanceIdentityReply { address, .. }
+            | Self::AllCallReply { address, .. }
+            | Self::LongAirAirSurveillance { address, .. }
+            | Self::ExtendedSquitter { address, .. }
+            | Self::ExtendedSquitterNonTransponder { address, .. }
+            | Self::CommBAltitudeReply { address, .. }
+            | Self::CommBIdentityReply { address, .. }
+            | Self::CommD { address, .. } => Some(*address),
+            Self::MilitaryExtendedSquitter { .. } | Self::Unknown { .. } => None,
+        }
+    }
+
+    /// The ADS-B payload of a DF17 or DF18 message
+    #[must_use]
+    pub fn extended_squitter(&self) -> Option<&ExtendedSquitter> {
+        match self {
+            Self::ExtendedSquitter { me, .. } => Some(me),
+            Self::ExtendedSquitterNonTransponder { me, .. } => me.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// ADS-B message, the ME field of DF17/DF18
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExtendedSquitter {
+    /// TC0: No position information
+    NoPosition {
+        /// Barometric altitude, `None` if not available or invalid
+        altitude: Option<Altitude>,
+    },
+    /// TC1-4: Aircraft identification and category
+    Identification {
+        tc: u8,
+        /// Emitter category as used by dump1090, set A-D in the high nibble, for example 0xA3
+        category: u8,
+        callsign: String,
+    },
+    /// TC5-8: Surface position
+    SurfacePosition {
+        tc: u8,
+        /// Ground speed in knots, `None` if not available
+        ground_speed: Option<f64>,
+        /// Ground track in degrees clockwise from true north, `None` if not valid
+        track: Option<f64>,
+        utc: bool,
+        cpr: CprFrame,
+    },
+    /// TC9-18 and TC20-22: Airborne position
+    AirbornePosition {
+        tc: u8,
+        ss: SurveillanceStatus,
+        /// Single antenna flag in version 0/1, NIC supplement B in version 2
+        nic_sb: bool,
+        /// Barometric altitude or GNSS height depending on `source`, `None` if not available or
+        /// invalid
+        altitude: Option<Altitude>,
+        source: AltitudeSource,
+        utc: bool,
+        cpr: CprFrame,
+    },
+    /// TC19: Airborne velocity
+    AirborneVelocity(AirborneVelocity),
+    /// TC28: Aircraft status
+    ///
+    /// Emergency and squawk are only sent in subtype 1, subtype 2 is a TCAS resolution advisory
+    /// broadcast with a different layout.
+    AircraftStatus { subtype: u8, emergency: Option<Emergency>, squawk: Option<Squawk> },
+    /// TC29: Target state and status
+    TargetStateAndStatus {
+        subtype: u8,
+        /// The whole ME field
+        me: [u8; 7],
+    },
+    /// TC31: Aircraft operational status
+    OperationalStatus {
+        subtype: u8,
+        /// ADS-B version number
+        version: u8,
+    },
+    /// TC23-27, TC30: Test, surface system status and reserved messages
+    Reserved {
+        tc: u8,
+        /// The whole ME field
+        me: [u8; 7],
+    },
+}
+
+impl ExtendedSquitter {
+    /// Type code, the first 5 bits of the ME field
+    #[must_use]
+    pub fn tc(&self) -> u8 {
+        match self {
+            Self::NoPosition { .. } => 0,
+            Self::Identification { tc, .. }
+            | Self::SurfacePosition { tc, .. }
+            | Self::AirbornePosition { tc, .. }
+            | Self::Reserved { tc, .. } => *tc,
+            Self::AirborneVelocity(_) => 19,
+            Self::AircraftStatus { .. } => 28,
+            Self::TargetStateAndStatus { .. } => 29,
+            Self::OperationalStatus { .. } => 31,
+        }
+    }
+}
+
+/// One half of a Compact Position Reporting pair
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CprFrame {
+    /// Odd (F=1) or even (F=0) frame
+    pub odd: bool,
+    /// 17 bit encoded latitude
+    pub lat: u32,
+    /// 17 bit encoded longitude
+    pub lon: u32,
+}
+
+/// Source of the altitude in an airborne position message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AltitudeSource {
+    /// Barometric altitude (TC9-18)
+    Baro,
+    /// GNSS height above the ellipsoid (TC20-22)
+    Gnss,
+}
+
+/// Surveillance status of an airborne position message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SurveillanceStatus {
+    NoCondition,
+    PermanentAlert,
+    TemporaryAlert,
+    Spi,
+}
+
+impl SurveillanceStatus {
+    /// A permanent or temporary alert, set on a squawk change like the flight status alert
+    #[must_use]
+    pub fn alert(self) -> bool {
+        matches!(self, Self::PermanentAlert | Self::TemporaryAlert)
+    }
+
+    #[must_use]
+    pub fn spi(self) -> bool {
+        self == Self::Spi
+    }
+}
+
+/// Emergency/priority status from an aircraft status message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Emergency {
+    None,
+    General,
+    Lifeguard,
+    MinimumFuel,
+    NoCommunications,
+    UnlawfulInterference,
+    DownedAircraft,
+    Reserved,
+}
+
+/// Flight status (FS) field of DF4/5/20/21
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FlightStatus(pub u8);
+
+impl FlightStatus {
+    #[must_use]
+    pub fn alert(self) -> bool {
+        matches!(self.0, 2..=4)
+    }
+
+    #[must_use]
+    pub fn spi(self) -> bool {
+        matches!(self.0, 4 | 5)
+    }
+
+    /// `None` if the status doesn't say
+    #[must_use]
+    pub fn on_ground(self) -> Option<bool> {
+        match self.0 {
+            0 | 2 => Some(false),
+            1 | 3 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Capability (CA) field of DF11/DF17
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Capability(pub u8);
+
+impl Capability {
+    /// `None` if the capability doesn't say
+    #[must_use]
+    pub fn on_ground(self) -> Option<bool> {
+        match self.0 {
+            4 => Some(true),
+            5 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Vertical status (VS) field of DF0/DF16
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerticalStatus {
+    Airborne,
+    OnGround,
+}
+
+/// Mode A code with one digit per nibble, 0x7700 is squawk 7700
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Squawk(pub u16);
+
+impl std::fmt::Display for Squawk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04x}", self.0)
+    }
+}
+
+/// Decode a 7 or 14 byte Mode S message
+///
+/// No CRC checking is done, this is up to the demodulator.
+pub fn decode(msg: &[u8]) -> Result<DownlinkFormat, &'static str> {
+    if msg.is_empty() {
+        return Err("empty message");
+    }
+
+    let msgtype = getbits(msg, 1, 5);
+    let msgbits = modes_message_len_by_type(msgtype);
+    if msg.len() * 8 < msgbits {
+        return Err("message too short for its downlink format");
+    }
+
+    // Address/Parity formats have the address overlaid on the CRC
+    let address = || modes_checksum(msg, msgbits);
+    let bytes = |first: usize| -> [u8; 7] {
+        let mut out = [0; 7];
+        out.copy_from_slice(&msg[first..first + 7]);
+        out
+    };
+
+    let msg = match msgtype {
+        0 => DownlinkFormat::ShortAirAirSurveillance {
+            vs: vertical_status(msg),
+            cc: getbits(msg, 7, 7) as u8,
+            sl: getbits(msg, 9, 11) as u8,
+            ri: getbits(msg, 14, 17) as u8,
+            altitude: decode_ac13_field(getbits(msg, 20, 32) as u16),
+            address: address(),
+        },
+        4 => DownlinkFormat::SurveillanceAltitudeReply {
+            fs: FlightStatus(getbits(msg, 6, 8) as u8),
+            dr: getbits(msg, 9, 13) as u8,
+            um: getbits(msg, 14, 19) as u8,
+            altitude: decode_ac13_field(getbits(msg, 20, 32) as u16),
+            address: address(),
+        },
+        5 => DownlinkFormat::SurveillanceIdentityReply {
+            fs: FlightStatus(getbits(msg, 6, 8) as u8),
+            dr: getbits(msg, 9, 13) as u8,
+            um: getbits(msg, 14, 19) as u8,
+            squawk: Squawk(decode_id13_field(getbits(msg, 20, 32))),
+            address: address(),
+        },
+        11 => DownlinkFormat::AllCallReply {
+            ca: Capability(getbits(msg, 6, 8) as u8),
+            address: getbits(msg, 9, 32) as u32,
+            iid: (address() & 0x7f) as u8,
+        },
+        16 => DownlinkFormat::LongAirAirSurveillance {
+            vs: vertical_status(msg),
+            sl: getbits(msg, 9, 11) as u8,
+            ri: getbits(msg, 14, 17) as u8,
+            altitude: decode_ac13_field(getbits(msg, 20, 32) as u16),
+            mv: bytes(4),
+            address: address(),
+        },
+        17 => DownlinkFormat::ExtendedSquitter {
+            ca: Capability(getbits(msg, 6, 8) as u8),
+            address: getbits(msg, 9, 32) as u32,
+            me: decode_extended_squitter(msg),
+        },
+        18 => {
+            let cf = getbits(msg, 6, 8) as u8;
+            DownlinkFormat::ExtendedSquitterNonTransponder {
+                cf,
+                address: getbits(msg, 9, 32) as u32,
+                me: matches!(cf, 0 | 1 | 2 | 5 | 6).then(|| decode_extended_squitter(msg)),
+            }
+        }
+        19 => {
+            let mut data = [0; 13];
+            data.copy_from_slice(&msg[1..14]);
+            DownlinkFormat::MilitaryExtendedSquitter { af: getbits(msg, 6, 8) as u8, data }
+        }
+        20 => DownlinkFormat::CommBAltitudeReply {
+            fs: FlightStatus(getbits(msg, 6, 8) as u8),
+            dr: getbits(msg, 9, 13) as u8,
+            um: getbits(msg, 14, 19) as u8,
+            altitude: decode_ac13_field(getbits(msg, 20, 32) as u16),
+            mb: bytes(4),
+            address: address(),
+        },
+        21 => DownlinkFormat::CommBIdentityReply {
+            fs: FlightStatus(getbits(msg, 6, 8) as u8),
+            dr: getbits(msg, 9, 13) as u8,
+            um: getbits(msg, 14, 19) as u8,
+            squawk: Squawk(decode_id13_field(getbits(msg, 20, 32))),
+            mb: bytes(4),
+            address: address(),
+        },
+        24..=31 => {
+            let mut md = [0; 10];
+            md.copy_from_slice(&msg[1..11]);
+            DownlinkFormat::CommD { nd: getbits(msg, 5, 8) as u8, md, address: address() }
+        }
+        df => DownlinkFormat::Unknown { df: df as u8 },
+    };
+
+    Ok(msg)
+}
+
+impl ModesMessage {
+    /// Decode the message into its typed fields, see [`decode`]
+    pub fn decode(&self) -> Result<DownlinkFormat, &'static str> {
+        decode(self.bytes())
+    }
+}
+
+fn vertical_status(msg: &[u8]) -> VerticalStatus {
+    if getbits(msg, 6, 6) == 1 {
+        VerticalStatus::OnGround
+    } else {
+        VerticalStatus::Airborne
+    }
+}
+
+// mode_s.c:decodeExtendedSquitter
+fn decode_extended_squitter(msg: &[u8]) -> ExtendedSquitter {
+    let mut me = [0; 7];
+    me.copy_from_slice(&msg[4..11]);
+
+    let tc = getbits(msg, 33, 37) as u8;
+    let subtype = getbits(msg, 38, 40) as u8;
+
+    match tc {
+        0 => ExtendedSquitter::NoPosition {
+            altitude: decode_ac12_field(getbits(msg, 41, 52) as u16),
+        },
+        1..=4 => {
+            let callsign = (0..8)
+                .map(|i| AIS_CHARSET[getbits(msg, 41 + i * 6, 46 + i * 6)] as char)
+                .collect::<String>()
+                .trim_end()
+                .to_string();
+            ExtendedSquitter::Identification {
+                tc,
+                category: ((0x0e - tc) << 4) | subtype,
+                callsign,
+            }
+        }
+        5..=8 => {
+            let track_valid = getbits(msg, 45, 45) == 1;
+            ExtendedSquitter::SurfacePosition {
+                tc,
+                ground_speed: decode_movement_field(getbits(msg, 38, 44) as u8),
+                track: track_valid.then(|| getbits(msg, 46, 52) as f64 * 360.0 / 128.0),
+                utc: getbits(msg, 53, 53) == 1,
+                cpr: cpr_frame(msg),
+            }
+        }
+        9..=18 | 20..=22 => {
+            let ac12 = getbits(msg, 41, 52) as u16;
+            let (altitude, source) = if tc < 19 {
+                (decode_ac12_field(ac12), AltitudeSource::Baro)
+            } else {
+                (decode_gnss_height(ac12), AltitudeSource::Gnss)
+            };
+            ExtendedSquitter::AirbornePosition {
+                tc,
+                ss: match getbits(msg, 38, 39) {
+                    0 => SurveillanceStatus::NoCondition,
+                    1 => SurveillanceStatus::PermanentAlert,
+                    2 => SurveillanceStatus::TemporaryAlert,
+                    _ => SurveillanceStatus::Spi,
+                },
+                nic_sb: getbits(msg, 40, 40) == 1,
+                altitude,
+                source,
+                utc: getbits(msg, 53, 53) == 1,
+                cpr: cpr_frame(msg),
+            }
+        }
+        19 => ExtendedSquitter::AirborneVelocity(decode_airborne_velocity(msg)),
+        28 => ExtendedSquitter::AircraftStatus {
+            subtype,
+            emergency: (subtype == 1).then(|| match getbits(msg, 41, 43) {
+                0 => Emergency::None,
+                1 => Emergency::General,
+                2 => Emergency::Lifeguard,
+                3 => Emergency::MinimumFuel,
+                4 => Emergency::NoCommunications,
+                5 => Emergency::UnlawfulInterference,
+                6 => Emergency::DownedAircraft,
+                _ => Emergency::Reserved,
+            }),
+            squawk: (subtype == 1).then(|| Squawk(decode_id13_field(getbits(msg, 44, 56)))),
+        },
+        29 => ExtendedSquitter::TargetStateAndStatus { subtype: getbits(msg, 38, 39) as u8, me },
+        31 => ExtendedSquitter::OperationalStatus { subtype, version: getbits(msg, 73, 75) as u8 },
+        _ => ExtendedSquitter::Reserved { tc, me },
+    }
+}
+
+fn cpr_frame(msg: &[u8]) -> CprFrame {
+    CprFrame {
+        odd: getbits(msg, 54, 54) == 1,
+        lat: getbits(msg, 55, 71) as u32,
+        lon: getbits(msg, 72, 88) as u32,
+    }
+}
+
+// Surface movement field, quantized ground speed in knots
+fn decode_movement_field(movement: u8) -> Option<f64> {
+    let m = f64::from(movement);
+    match movement {
+        1 => Some(0.0),
+        2..=8 => Some((m - 1.0) * 0.125),
+        9..=12 => Some(1.0 + (m - 9.0) * 0.25),
+        13..=38 => Some(2.0 + (m - 13.0) * 0.5),
+        39..=93 => Some(15.0 + (m - 39.0)),
+        94..=108 => Some(70.0 + (m - 94.0) * 2.0),
+        109..=123 => Some(100.0 + (m - 109.0) * 5.0),
+        124 => Some(175.0),
+        // 0: no information, 125-127: reserved
+        _ => None,
+    }
+}
@@ -1,6 +1,24 @@
 // This module includes functionality translated from mode_s.c
 
-use crate::{MODES_LONG_MSG_BYTES, MODES_SHORT_MSG_BYTES};
+mod altitude;
+mod decode;
+mod velocity;
+
+pub use altitude::{
+    decode_ac12_field, decode_ac13_field, decode_gnss_height, mode_a_to_mode_c, Altitude,
+    AltitudeUnit,
+};
+pub use decode::{
+    decode, AltitudeSource, Capability, CprFrame, DownlinkFormat, Emergency, ExtendedSquitter,
+    FlightStatus, Squawk, SurveillanceStatus, VerticalStatus,
+};
+pub use velocity::{AirborneVelocity, AirspeedType, Velocity, VerticalRateSource};
+
+use crate::crc::{
+    modes_checksum, modes_checksum_diagnose, modes_checksum_fix, ErrorInfo, DF11_SYNDROME_MASK,
+};
+use crate::icao_filter::IcaoFilter;
+use crate::{ModesMessage, MODES_LONG_MSG_BYTES, MODES_SHORT_MSG_BYTES};
 
 // mode_s.c:215
 #[must_use]
@@ -22,9 +40,129 @@ pub fn getbits(data: &[u8], firstbit_1idx: usize, lastbit_1idx: usize) -> usize
     ans
 }
 
+// mode_s.c:modesMessageLenByType
+#[must_use]
+pub fn modes_message_len_by_type(msgtype: usize) -> usize {
+    if (msgtype & 0x10) != 0 {
+        MODES_LONG_MSG_BYTES * 8
+    } else {
+        MODES_SHORT_MSG_BYTES * 8
+    }
+}
+
+// mode_s.c:decodeID13Field
+// Rearrange the 13 bit identity field into one Mode A digit per nibble (ABCD)
+#[must_use]
+pub fn decode_id13_field(id13_field: usize) -> u16 {
+    let mut hex_gillham: u16 = 0;
+
+    if id13_field & 0x1000 != 0 {
+        hex_gillham |= 0x0010; // Bit 12 = C1
+    }
+    if id13_field & 0x0800 != 0 {
+        hex_gillham |= 0x1000; // Bit 11 = A1
+    }
+    if id13_field & 0x0400 != 0 {
+        hex_gillham |= 0x0020; // Bit 10 = C2
+    }
+    if id13_field & 0x0200 != 0 {
+        hex_gillham |= 0x2000; // Bit  9 = A2
+    }
+    if id13_field & 0x0100 != 0 {
+        hex_gillham |= 0x0040; // Bit  8 = C4
+    }
+    if id13_field & 0x0080 != 0 {
+        hex_gillham |= 0x4000; // Bit  7 = A4
+    }
+    // Bit 6 = X or M
+    if id13_field & 0x0020 != 0 {
+        hex_gillham |= 0x0100; // Bit  5 = B1
+    }
+    if id13_field & 0x0010 != 0 {
+        hex_gillham |= 0x0001; // Bit  4 = D1 or Q
+    }
+    if id13_field & 0x0008 != 0 {
+        hex_gillham |= 0x0200; // Bit  3 = B2
+    }
+    if id13_field & 0x0004 != 0 {
+        hex_gillham |= 0x0002; // Bit  2 = D2
+    }
+    if id13_field & 0x0002 != 0 {
+        hex_gillham |= 0x0400; // Bit  1 = B4
+    }
+    if id13_field & 0x0001 != 0 {
+        hex_gillham |= 0x0004; // Bit  0 = D4
+    }
+
+    hex_gillham
+}
+
+// mode_s.c:decodeModesMessage
+// Returns the address of a CRC-clean DF11 (IID 0), DF17 or DF18 message. These are the only
+// messages that carry their address in the clear with no parity overlay, so they are used to
+// populate the ICAO filter for the address/parity formats.
+#[must_use]
+pub(crate) fn verified_address(msg: &[u8]) -> Option<u32> {
+    if msg.len() < MODES_SHORT_MSG_BYTES {
+        return None;
+    }
+
+    let msgtype = getbits(msg, 1, 5);
+    let msgbits = match msgtype {
+        11 => MODES_SHORT_MSG_BYTES * 8,
+        17 | 18 if msg.len() >= MODES_LONG_MSG_BYTES => MODES_LONG_MSG_BYTES * 8,
+        _ => return None,
+    };
+
+    let crc = modes_checksum(msg, msgbits);
+    if crc != 0 {
+        return None;
+    }
+
+    Some(getbits(msg, 9, 32) as u32)
+}
+
+// mode_s.c:correct_aa_field
+fn correct_aa_field(addr: &mut u32, ei: &ErrorInfo) {
+    for &bit in ei.bits() {
+        if (8..=31).contains(&bit) {
+            *addr ^= 1 << (31 - bit);
+        }
+    }
+}
+
+// mode_s.c:decodeModesMessage
+// Repair up to `nfix_crc` bit errors in a DF11, DF17 or DF18 message in place, returning the
+// positions of the corrected bits. Other formats have their address overlaid on the parity and
+// can't be corrected.
+pub(crate) fn correct_modes_message(msg: &mut [u8], nfix_crc: usize) -> Vec<usize> {
+    let msgtype = getbits(msg, 1, 5);
+    let msgbits = modes_message_len_by_type(msgtype);
+    if msg.len() * 8 < msgbits {
+        return vec![];
+    }
+
+    let ei = match msgtype {
+        11 => {
+            let crc = modes_checksum(msg, msgbits) & DF11_SYNDROME_MASK;
+            modes_checksum_diagnose(crc, msgbits, nfix_crc.min(1))
+        }
+        17 | 18 => modes_checksum_diagnose(modes_checksum(msg, msgbits), msgbits, nfix_crc),
+        _ => None,
+    };
+
+    match ei {
+        Some(ei) if ei.errors > 0 => {
+            modes_checksum_fix(msg, &ei);
+            ei.bits().to_vec()
+        }
+        _ => vec![],
+    }
+}
+
 // mode_s.c:289
 #[must_use]
-pub fn score_modes_message(msg: &[u8]) -> i32 {
+pub(crate) fn score_modes_message(msg: &[u8], icao_filter: &IcaoFilter, nfix_crc: usize) -> i32 {
     let validbits = msg.len() * 8;
 
     if validbits < 56 {
@@ -32,8 +170,7 @@ pub fn score_modes_message(msg: &[u8]) -> i32 {
     }
 
     let msgtype = getbits(msg, 1, 5);
-    let msgbits =
-        if (msgtype & 0x10) != 0 { MODES_LONG_MSG_BYTES * 8 } else { MODES_SHORT_MSG_BYTES * 8 };
+    let msgbits = modes_message_len_by_type(msgtype);
 
     if validbits < msgbits {
         return -2;
@@ -42,7 +179,7 @@ pub fn score_modes_message(msg: &[u8]) -> i32 {
         return -2;
     }
 
-    let crc = super::crc::modes_checksum(msg, msgbits);
+    let crc = modes_checksum(msg, msgbits);
 
     match msgtype {
         0 | 4 | 5 | 16 | 24 | 25 | 26 | 27 | 28 | 29 | 30 | 31 => {
@@ -59,7 +196,7 @@ pub fn score_modes_message(msg: &[u8]) -> i32 {
             // 30: Comm-D (ELM)
             // 31: Comm-D (ELM)
 
-            if super::icao_filter::icao_filter_test(crc) {
+            if icao_filter.test(crc) {
                 1000
             } else {
                 -1
@@ -68,32 +205,47 @@ pub fn score_modes_message(msg: &[u8]) -> i32 {
         11 => {
             // 11: All-call reply
             let iid = crc & 0x7f;
-            let crc = crc & 0x00ff_ff80;
-            let addr = getbits(msg, 9, 32) as u32;
-
-            match (crc, iid, super::icao_filter::icao_filter_test(addr)) {
-                (0, 0, true) => 1600,
-                (0, 0, false) => 750,
-                (0, _, true) => 1000,
-                (0, _, false) => -1,
-                (_, _, _) => -2,
+            let crc = crc & DF11_SYNDROME_MASK;
+            let mut addr = getbits(msg, 9, 32) as u32;
+
+            // two bit errors are ambiguous in DF11, never try to fix more than one
+            let Some(ei) = modes_checksum_diagnose(crc, msgbits, nfix_crc.min(1)) else {
+                return -2; // can't correct errors
+            };
+
+            // fix any errors in the address field
+            correct_aa_field(&mut addr, &ei);
+
+            let errors = ei.errors as i32 + 1;
+            match (iid, icao_filter.test(addr)) {
+                (0, true) => 1600 / errors,
+                (0, false) => 750 / errors,
+                (_, true) => 1000 / errors,
+                (_, false) => -1,
             }
         }
         17 | 18 => {
             // 17: Extended squitter
             // 18: Extended squitter/non-transponder
-            let addr = getbits(msg, 9, 32) as u32;
+            let Some(ei) = modes_checksum_diagnose(crc, msgbits, nfix_crc) else {
+                return -2; // can't correct errors
+            };
 
-            match (crc, super::icao_filter::icao_filter_test(addr)) {
-                (0, true) => 1800,
-                (0, false) => 1400,
-                (_, _) => -2,
+            // fix any errors in the address field
+            let mut addr = getbits(msg, 9, 32) as u32;
+            correct_aa_field(&mut addr, &ei);
+
+            let errors = ei.errors as i32 + 1;
+            if icao_filter.test(addr) {
+                1800 / errors
+            } else {
+                1400 / errors
             }
         }
         20 | 21 => {
             // 20: Comm-B, altitude reply
             // 21: Comm-B, identity reply
-            match super::icao_filter::icao_filter_test(crc) {
+            match icao_filter.test(crc) {
                 true => 1000,
                 false => -2,
             }
@@ -101,3 +253,58 @@ pub fn score_modes_message(msg: &[u8]) -> i32 {
         _ => -2,
     }
 }
+
+// mode_s.c:decodeModesMessage
+/// Checks messages demodulated elsewhere, such as by other receivers on the network, the way
+/// the demodulator checks its own
+///
+/// DF11, DF17 and DF18 need a valid parity, after correcting up to `nfix_crc` bit errors. The
+/// other formats have their address overlaid on the parity, so they are only accepted from
+/// addresses that a verified DF11, DF17 or DF18 was recently seen from.
+#[derive(Clone, Debug)]
+pub struct MessageValidator {
+    icao_filter: IcaoFilter,
+    nfix_crc: usize,
+}
+
+impl Default for MessageValidator {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl MessageValidator {
+    #[must_use]
+    pub fn new(nfix_crc: usize) -> Self {
+        Self { icao_filter: IcaoFilter::default(), nfix_crc }
+    }
+
+    /// Score the message and repair its bit errors
+    ///
+    /// Returns `false` if the message should be dropped, otherwise `score` and `corrected_bits`
+    /// are set like for a demodulated message.
+    pub fn validate(&mut self, msg: &mut ModesMessage) -> bool {
+        self.icao_filter.expire();
+
+        let score = score_modes_message(msg.bytes(), &self.icao_filter, self.nfix_crc);
+        if score < 0 {
+            return false;
+        }
+        msg.score = score;
+        msg.corrected_bits = correct_modes_message(&mut msg.data[..msg.len], self.nfix_crc);
+
+        // corrected messages are not trusted enough to add new addresses
+        if msg.corrected_bits.is_empty() {
+            self.learn(msg);
+        }
+        true
+    }
+
+    /// Accept the address/parity formats from the address of `msg` if it is a verified DF11,
+    /// DF17 or DF18, for messages that were already checked such as the demodulator's
+    pub fn learn(&mut self, msg: &ModesMessage) {
+        if let Some(addr) = verified_address(msg.bytes()) {
+            self.icao_filter.add(addr);
+        }
+    }
+}
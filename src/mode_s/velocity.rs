@@ -0,0 +1,136 @@
+// This module includes functionality translated from mode_s.c:decodeESAirborneVelocity
+
+use super::getbits;
+
+/// Horizontal part of an airborne velocity message
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Velocity {
+    /// Subtypes 1 and 2: velocity over ground
+    GroundSpeed {
+        /// East-west velocity in knots, positive east
+        ew: Option<i32>,
+        /// North-south velocity in knots, positive north
+        ns: Option<i32>,
+        /// Ground speed in knots, only if both components are available
+        ground_speed: Option<f64>,
+        /// Ground track in degrees clockwise from true north, only if moving
+        track: Option<f64>,
+    },
+    /// Subtypes 3 and 4: airspeed and heading
+    Airspeed {
+        /// Airspeed in knots
+        airspeed: Option<u32>,
+        airspeed_type: AirspeedType,
+        /// Heading in degrees clockwise from magnetic or true north
+        heading: Option<f64>,
+    },
+}
+
+/// Airspeed reported by subtypes 3 and 4
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AirspeedType {
+    Indicated,
+    True,
+}
+
+/// Source of the vertical rate in an airborne velocity message
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VerticalRateSource {
+    Gnss,
+    Baro,
+}
+
+/// Decoded TC19 airborne velocity fields
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AirborneVelocity {
+    /// 1 and 3 subsonic, 2 and 4 supersonic
+    pub subtype: u8,
+    /// Intent change flag
+    pub intent_change: bool,
+    /// Navigation accuracy category for velocity
+    pub nac_v: u8,
+    /// `None` for the reserved subtypes 0 and 5-7
+    pub velocity: Option<Velocity>,
+    /// Vertical rate in ft/min, positive up
+    pub vertical_rate: Option<i32>,
+    pub vertical_rate_source: VerticalRateSource,
+    /// GNSS altitude minus barometric altitude in feet
+    pub gnss_baro_diff: Option<i32>,
+}
+
+impl AirborneVelocity {
+    /// Speeds in supersonic subtypes are reported in 4 knot units
+    #[must_use]
+    pub fn supersonic(&self) -> bool {
+        matches!(self.subtype, 2 | 4)
+    }
+}
+
+// ME bits are numbered from 1 like `getbits`, offset by the 32 bits before the ME field
+fn me_bits(msg: &[u8], first: usize, last: usize) -> usize {
+    getbits(msg, first + 32, last + 32)
+}
+
+// Magnitude field where 0 means not available, with an optional sign bit
+fn signed_field(raw: usize, negative: bool, scale: i32) -> Option<i32> {
+    if raw == 0 {
+        return None;
+    }
+    let value = (raw as i32 - 1) * scale;
+    Some(if negative { -value } else { value })
+}
+
+pub(super) fn decode_airborne_velocity(msg: &[u8]) -> AirborneVelocity {
+    let subtype = me_bits(msg, 6, 8) as u8;
+    let speed_scale = if matches!(subtype, 2 | 4) { 4 } else { 1 };
+
+    let velocity = match subtype {
+        1 | 2 => {
+            let ew = signed_field(me_bits(msg, 15, 24), me_bits(msg, 14, 14) == 1, speed_scale);
+            let ns = signed_field(me_bits(msg, 26, 35), me_bits(msg, 25, 25) == 1, speed_scale);
+
+            // Compute velocity and angle from the two speed components
+            let (ground_speed, track) = match (ew, ns) {
+                (Some(ew), Some(ns)) => {
+                    let (ew, ns) = (f64::from(ew), f64::from(ns));
+                    let ground_speed = ew.hypot(ns);
+                    // We don't want negative values but a 0-360 scale
+                    let track =
+                        (ground_speed > 0.0).then(|| ew.atan2(ns).to_degrees().rem_euclid(360.0));
+                    (Some(ground_speed), track)
+                }
+                _ => (None, None),
+            };
+            Some(Velocity::GroundSpeed { ew, ns, ground_speed, track })
+        }
+        3 | 4 => {
+            let airspeed = match me_bits(msg, 26, 35) {
+                0 => None,
+                raw => Some((raw as u32 - 1) * speed_scale as u32),
+            };
+            let airspeed_type = if me_bits(msg, 25, 25) == 1 {
+                AirspeedType::True
+            } else {
+                AirspeedType::Indicated
+            };
+            let heading =
+                (me_bits(msg, 14, 14) == 1).then(|| me_bits(msg, 15, 24) as f64 * 360.0 / 1024.0);
+            Some(Velocity::Airspeed { airspeed, airspeed_type, heading })
+        }
+        _ => None,
+    };
+
+    AirborneVelocity {
+        subtype,
+        intent_change: me_bits(msg, 9, 9) == 1,
+        nac_v: me_bits(msg, 11, 13) as u8,
+        velocity,
+        vertical_rate: signed_field(me_bits(msg, 38, 46), me_bits(msg, 37, 37) == 1, 64),
+        vertical_rate_source: if me_bits(msg, 36, 36) == 1 {
+            VerticalRateSource::Baro
+        } else {
+            VerticalRateSource::Gnss
+        },
+        gnss_baro_diff: signed_field(me_bits(msg, 50, 56), me_bits(msg, 49, 49) == 1, 25),
+    }
+}
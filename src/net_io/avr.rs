@@ -0,0 +1,70 @@
+// net_io.c:modesSendRawOutput, net_io.c:decodeHexMessage
+
+use crate::ModesMessage;
+
+const TIMESTAMP_MASK: u64 = 0xffff_ffff_ffff;
+
+/// Encode a message as an AVR line, `*8D4840D6202CC371C32CE0576098;` and a newline
+#[must_use]
+pub fn encode_avr(msg: &ModesMessage) -> String {
+    let mut out = String::with_capacity(2 * msg.len + 3);
+    out.push('*');
+    push_hex(&mut out, msg.bytes());
+    out.push_str(";\n");
+    out
+}
+
+/// Encode a message as an AVR line with the 48 bit 12MHz timestamp used for MLAT,
+/// `@0123456789AB8D4840D6202CC371C32CE0576098;` and a newline
+#[must_use]
+pub fn encode_avr_mlat(msg: &ModesMessage) -> String {
+    let mut out = String::with_capacity(2 * msg.len + 15);
+    out.push_str(&format!("@{:012X}", msg.timestamp_12mhz & TIMESTAMP_MASK));
+    push_hex(&mut out, msg.bytes());
+    out.push_str(";\n");
+    out
+}
+
+/// Decode one AVR line, either `*<hex>;` or the MLAT variant `@<timestamp><hex>;`
+///
+/// Surrounding whitespace and a missing `;` are accepted. The signal level isn't part of the
+/// format, so `rssi` is negative infinity.
+pub fn decode_avr(line: &str) -> Result<ModesMessage, &'static str> {
+    let line = line.trim();
+    let line = line.strip_suffix(';').unwrap_or(line);
+    if !line.is_ascii() {
+        return Err("invalid AVR line");
+    }
+
+    let (timestamp_12mhz, hex) = if let Some(hex) = line.strip_prefix('*') {
+        (0, hex)
+    } else if let Some(rest) = line.strip_prefix('@') {
+        if rest.len() < 12 {
+            return Err("AVR timestamp too short");
+        }
+        let timestamp =
+            u64::from_str_radix(&rest[..12], 16).map_err(|_| "invalid AVR timestamp")?;
+        (timestamp, &rest[12..])
+    } else {
+        return Err("invalid AVR line");
+    };
+
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("invalid hex digit");
+    }
+    if hex.len() % 2 != 0 {
+        return Err("odd number of hex digits");
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "invalid hex digit")?;
+    ModesMessage::from_bytes(&bytes, timestamp_12mhz, f64::NEG_INFINITY)
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    for b in bytes {
+        out.push_str(&format!("{b:02X}"));
+    }
+}
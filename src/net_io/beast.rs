@@ -0,0 +1,150 @@
+// net_io.c:modesSendBeastOutput
+
+use crate::ModesMessage;
+
+/// Start of every Beast frame, doubled when it appears in the frame body
+pub const BEAST_ESCAPE: u8 = 0x1a;
+
+/// Frame type for a Mode A/C reply, skipped when decoding
+pub const BEAST_MODE_AC: u8 = b'1';
+/// Frame type for a 56 bit Mode S message
+pub const BEAST_MODES_SHORT: u8 = b'2';
+/// Frame type for a 112 bit Mode S message
+pub const BEAST_MODES_LONG: u8 = b'3';
+
+const TIMESTAMP_MASK: u64 = 0xffff_ffff_ffff;
+
+/// Signal byte of a Beast frame: the signal amplitude scaled to 0..=255, from `rssi` in dBFS
+///
+/// Any signal at all is reported as at least 1, like dump1090.
+#[must_use]
+pub fn beast_signal_level(rssi: f64) -> u8 {
+    let power = 10_f64.powf(rssi / 10.0);
+    let level = (power.sqrt() * 255.0).round().clamp(0.0, 255.0) as u8;
+    if power > 0.0 && level == 0 {
+        1
+    } else {
+        level
+    }
+}
+
+/// Signal level in dBFS from the signal byte of a Beast frame, the inverse of
+/// [`beast_signal_level`]
+#[must_use]
+pub fn beast_rssi(level: u8) -> f64 {
+    20.0 * (f64::from(level) / 255.0).log10()
+}
+
+/// Encode a message as a Mode-S Beast binary frame
+///
+/// The frame is `0x1a`, the type, a 48 bit big endian 12MHz timestamp, the signal level and the
+/// message bytes, with every `0x1a` after the first byte doubled.
+#[must_use]
+pub fn encode_beast(msg: &ModesMessage) -> Vec<u8> {
+    let msg_type = if msg.len > 7 { BEAST_MODES_LONG } else { BEAST_MODES_SHORT };
+    let timestamp = (msg.timestamp_12mhz & TIMESTAMP_MASK).to_be_bytes();
+
+    let mut out = Vec::with_capacity(2 * (2 + 6 + 1 + msg.len));
+    out.push(BEAST_ESCAPE);
+    out.push(msg_type);
+    let signal = [beast_signal_level(msg.rssi)];
+    let body = timestamp[2..].iter().chain(&signal).chain(msg.bytes());
+    for &b in body {
+        out.push(b);
+        if b == BEAST_ESCAPE {
+            out.push(BEAST_ESCAPE);
+        }
+    }
+    out
+}
+
+/// Streaming decoder for Mode-S Beast binary frames, as read from a tcp connection
+///
+/// Bytes can be fed in chunks of any size, a frame split between chunks is kept until the rest
+/// of it arrives. Mode A/C frames and messages that don't have a valid length are skipped, other
+/// frame types are skipped by resyncing on the next frame start.
+#[derive(Debug, Default)]
+pub struct BeastDecoder {
+    pending: Vec<u8>,
+}
+
+// Result of parsing one frame at the start of a buffer
+enum Frame {
+    Incomplete,
+    Invalid,
+    Complete { consumed: usize, message: Option<ModesMessage> },
+}
+
+impl BeastDecoder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode every frame completed by `data`
+    pub fn decode(&mut self, data: &[u8]) -> Vec<ModesMessage> {
+        self.pending.extend_from_slice(data);
+
+        let mut messages = vec![];
+        let mut start = 0;
+        loop {
+            // skip to the next frame start
+            let Some(offset) = self.pending[start..].iter().position(|&b| b == BEAST_ESCAPE) else {
+                start = self.pending.len();
+                break;
+            };
+            start += offset;
+
+            match parse_frame(&self.pending[start..]) {
+                Frame::Incomplete => break,
+                Frame::Invalid => start += 1,
+                Frame::Complete { consumed, message } => {
+                    start += consumed;
+                    messages.extend(message);
+                }
+            }
+        }
+        self.pending.drain(..start);
+        messages
+    }
+}
+
+// Unescape one frame from `buf`, which starts with `BEAST_ESCAPE`
+fn parse_frame(buf: &[u8]) -> Frame {
+    let Some(&msg_type) = buf.get(1) else {
+        return Frame::Incomplete;
+    };
+    let msg_len = match msg_type {
+        BEAST_MODE_AC => 2,
+        BEAST_MODES_SHORT => 7,
+        BEAST_MODES_LONG => 14,
+        _ => return Frame::Invalid,
+    };
+
+    // 6 timestamp bytes, the signal byte and the message
+    let mut body = Vec::with_capacity(6 + 1 + msg_len);
+    let mut i = 2;
+    while body.len() < body.capacity() {
+        let Some(&b) = buf.get(i) else {
+            return Frame::Incomplete;
+        };
+        if b == BEAST_ESCAPE {
+            match buf.get(i + 1) {
+                Some(&BEAST_ESCAPE) => i += 1,
+                Some(_) => return Frame::Invalid, // start of the next frame, this one is cut short
+                None => return Frame::Incomplete,
+            }
+        }
+        body.push(b);
+        i += 1;
+    }
+
+    let mut timestamp = [0; 8];
+    timestamp[2..].copy_from_slice(&body[..6]);
+    let message = (msg_type != BEAST_MODE_AC)
+        .then(|| {
+            ModesMessage::from_bytes(&body[7..], u64::from_be_bytes(timestamp), beast_rssi(body[6]))
+        })
+        .and_then(Result::ok);
+    Frame::Complete { consumed: i, message }
+}
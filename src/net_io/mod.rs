@@ -0,0 +1,7 @@
+// This module includes functionality translated from net_io.c
+//
+// Encoders for the network formats served by dump1090
+
+pub mod avr;
+pub mod beast;
+pub mod sbs;
@@ -0,0 +1,132 @@
+// net_io.c:modesSendSBSOutput
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::mode_s::{DownlinkFormat, ExtendedSquitter, Squawk};
+use crate::tracker::{Aircraft, Tracked};
+use crate::utils::civil_from_days;
+use crate::ModesMessage;
+
+// Columns after the times
+const COLUMNS: usize = 12;
+const CALLSIGN: usize = 0;
+const ALTITUDE: usize = 1;
+const GROUND_SPEED: usize = 2;
+const TRACK: usize = 3;
+const LAT: usize = 4;
+const LON: usize = 5;
+const VERTICAL_RATE: usize = 6;
+const SQUAWK: usize = 7;
+const ALERT: usize = 8;
+const EMERGENCY: usize = 9;
+const SPI: usize = 10;
+const ON_GROUND: usize = 11;
+
+// Columns filled for each transmission type, from the BaseStation socket data documentation
+const COLUMNS_BY_TYPE: [&[usize]; 8] = [
+    &[CALLSIGN],
+    &[ALTITUDE, GROUND_SPEED, TRACK, LAT, LON, ON_GROUND],
+    &[ALTITUDE, LAT, LON, ALERT, EMERGENCY, SPI, ON_GROUND],
+    &[GROUND_SPEED, TRACK, VERTICAL_RATE],
+    &[ALTITUDE, ALERT, SPI, ON_GROUND],
+    &[ALTITUDE, SQUAWK, ALERT, EMERGENCY, SPI, ON_GROUND],
+    &[ALTITUDE, ON_GROUND],
+    &[ON_GROUND],
+];
+
+// Squawks that set the emergency flag: hijack, radio failure, emergency
+const EMERGENCY_SQUAWKS: [u16; 3] = [0x7500, 0x7600, 0x7700];
+
+/// BaseStation transmission type for a message, `None` if it has no SBS equivalent
+///
+/// 1: identification, 2: surface position, 3: airborne position, 4: airborne velocity,
+/// 5: surveillance altitude (DF4/20), 6: surveillance identity (DF5/21),
+/// 7: air to air (DF0/16), 8: all call reply (DF11)
+#[must_use]
+pub fn sbs_message_type(frame: &DownlinkFormat) -> Option<u8> {
+    match frame {
+        DownlinkFormat::SurveillanceAltitudeReply { .. }
+        | DownlinkFormat::CommBAltitudeReply { .. } => Some(5),
+        DownlinkFormat::SurveillanceIdentityReply { .. }
+        | DownlinkFormat::CommBIdentityReply { .. } => Some(6),
+        DownlinkFormat::ShortAirAirSurveillance { .. }
+        | DownlinkFormat::LongAirAirSurveillance { .. } => Some(7),
+        DownlinkFormat::AllCallReply { .. } => Some(8),
+        _ => match frame.extended_squitter()? {
+            ExtendedSquitter::Identification { .. } => Some(1),
+            ExtendedSquitter::SurfacePosition { .. } => Some(2),
+            ExtendedSquitter::AirbornePosition { .. } => Some(3),
+            ExtendedSquitter::AirborneVelocity(_) => Some(4),
+            _ => None,
+        },
+    }
+}
+
+/// Encode a message as a BaseStation `MSG` line, terminated by `\r\n`
+///
+/// `aircraft` is the tracker state after merging `msg`. Only the fields that belong to the
+/// transmission type and were updated by the last message of the aircraft are filled, so that
+/// for example a position is only sent when this message completed a CPR decode. `time` is
+/// written as both the generated and logged time, in UTC.
+///
+/// Returns `None` for messages without an SBS equivalent or from another aircraft.
+#[must_use]
+pub fn encode_sbs(msg: &ModesMessage, aircraft: &Aircraft, time: SystemTime) -> Option<String> {
+    let frame = msg.decode().ok()?;
+    if frame.address()? != aircraft.address {
+        return None;
+    }
+    let msg_type = sbs_message_type(&frame)?;
+
+    // only values from the message that was just merged
+    fn fresh<T: Clone>(aircraft: &Aircraft, field: &Option<Tracked<T>>) -> Option<T> {
+        field.as_ref().filter(|f| f.updated == aircraft.last_seen).map(|f| f.value.clone())
+    }
+    let position = fresh(aircraft, &aircraft.position);
+    let squawk = fresh(aircraft, &aircraft.squawk);
+    let values: [Option<String>; COLUMNS] = [
+        fresh(aircraft, &aircraft.callsign),
+        fresh(aircraft, &aircraft.altitude).map(|a| a.in_feet().to_string()),
+        fresh(aircraft, &aircraft.ground_speed).map(|gs| format!("{gs:.0}")),
+        fresh(aircraft, &aircraft.track).map(|track| format!("{track:.0}")),
+        position.map(|(lat, _)| format!("{lat:.5}")),
+        position.map(|(_, lon)| format!("{lon:.5}")),
+        fresh(aircraft, &aircraft.vertical_rate).map(|(vr, _)| vr.to_string()),
+        squawk.map(|s| s.to_string()),
+        fresh(aircraft, &aircraft.alert).map(flag),
+        squawk.map(|Squawk(s)| flag(EMERGENCY_SQUAWKS.contains(&s))),
+        fresh(aircraft, &aircraft.spi).map(flag),
+        fresh(aircraft, &aircraft.on_ground).map(flag),
+    ];
+
+    let time = sbs_time(time);
+    let mut out = format!("MSG,{msg_type},1,1,{:06X},1,{time},{time}", aircraft.address);
+    let used = COLUMNS_BY_TYPE[usize::from(msg_type - 1)];
+    for (column, value) in values.iter().enumerate() {
+        out.push(',');
+        if used.contains(&column) {
+            out.push_str(value.as_deref().unwrap_or(""));
+        }
+    }
+    out.push_str("\r\n");
+    Some(out)
+}
+
+// -1 is true in the BaseStation format
+fn flag(value: bool) -> String {
+    if value { "-1" } else { "0" }.to_string()
+}
+
+// `YYYY/MM/DD,HH:MM:SS.mmm` in UTC
+fn sbs_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!(
+        "{year:04}/{month:02}/{day:02},{:02}:{:02}:{:02}.{:03}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
@@ -0,0 +1,131 @@
+// Client for the `rtl_tcp` server from librtlsdr, which streams the samples of a remote
+// rtlsdr dongle as unsigned 8 bit IQ
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use num_complex::Complex;
+
+use crate::convert::SampleFormat;
+
+/// First bytes sent by the server, followed by the tuner type and gain count
+pub const RTL_TCP_MAGIC: &[u8; 4] = b"RTL0";
+
+// Commands are one byte followed by a 32 bit big endian parameter
+const CMD_SET_FREQUENCY: u8 = 0x01;
+const CMD_SET_SAMPLE_RATE: u8 = 0x02;
+const CMD_SET_GAIN_MODE: u8 = 0x03;
+const CMD_SET_GAIN: u8 = 0x04;
+
+/// The dongle information header sent by the server when a client connects
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DongleInfo {
+    /// Tuner type as numbered by librtlsdr, 5 is the R820T
+    pub tuner_type: u32,
+    /// Number of gain steps the tuner supports
+    pub gain_count: u32,
+}
+
+/// Tuner gain setting
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RtlTcpGain {
+    /// Let the tuner pick the gain
+    Auto,
+    /// Manual gain in dB, rounded by the server to the closest supported step
+    Manual(f64),
+}
+
+/// A connection to an `rtl_tcp` server
+#[derive(Debug)]
+pub struct RtlTcpClient {
+    stream: TcpStream,
+    info: DongleInfo,
+    // byte left over from the last read that didn't make up a whole sample
+    partial: Option<u8>,
+    // read buffer for the raw IQ bytes
+    raw: Vec<u8>,
+}
+
+impl RtlTcpClient {
+    /// Connect and read the dongle information header
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+
+        let mut header = [0; 12];
+        stream.read_exact(&mut header)?;
+        if &header[..4] != RTL_TCP_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an rtl_tcp server"));
+        }
+        let info = DongleInfo {
+            tuner_type: u32::from_be_bytes(header[4..8].try_into().unwrap()),
+            gain_count: u32::from_be_bytes(header[8..12].try_into().unwrap()),
+        };
+
+        Ok(Self { stream, info, partial: None, raw: vec![] })
+    }
+
+    #[must_use]
+    pub fn info(&self) -> DongleInfo {
+        self.info
+    }
+
+    pub fn set_frequency(&mut self, hz: u32) -> io::Result<()> {
+        self.command(CMD_SET_FREQUENCY, hz)
+    }
+
+    pub fn set_sample_rate(&mut self, hz: u32) -> io::Result<()> {
+        self.command(CMD_SET_SAMPLE_RATE, hz)
+    }
+
+    /// Switch between automatic and manual gain, and set the manual gain
+    pub fn set_gain(&mut self, gain: RtlTcpGain) -> io::Result<()> {
+        match gain {
+            RtlTcpGain::Auto => self.command(CMD_SET_GAIN_MODE, 0),
+            RtlTcpGain::Manual(db) => {
+                self.command(CMD_SET_GAIN_MODE, 1)?;
+                // in tenths of a dB
+                self.command(CMD_SET_GAIN, (db * 10.0).round() as i32 as u32)
+            }
+        }
+    }
+
+    fn command(&mut self, cmd: u8, param: u32) -> io::Result<()> {
+        let mut buf = [0; 5];
+        buf[0] = cmd;
+        buf[1..].copy_from_slice(&param.to_be_bytes());
+        self.stream.write_all(&buf)
+    }
+
+    /// Read up to `buf.len()` samples, blocking until at least one is available
+    ///
+    /// Returns 0 once the server closes the connection.
+    pub fn read_samples(&mut self, buf: &mut [Complex<i16>]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.raw.resize(2 * buf.len(), 0);
+        let mut len = 0;
+        if let Some(i) = self.partial.take() {
+            self.raw[0] = i;
+            len = 1;
+        }
+
+        // a sample needs both its bytes
+        while len < 2 {
+            match self.stream.read(&mut self.raw[len..]) {
+                Ok(0) => return Ok(0),
+                Ok(read) => len += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        if len % 2 == 1 {
+            self.partial = Some(self.raw[len - 1]);
+        }
+
+        let samples = SampleFormat::Cu8.to_cs16(&self.raw[..len / 2 * 2], buf);
+        Ok(samples)
+    }
+}
@@ -0,0 +1,287 @@
+// SigMF recordings (https://sigmf.org): raw samples in `<name>.sigmf-data`, described by the
+// JSON in `<name>.sigmf-meta`
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::convert::SampleFormat;
+use crate::source::RawIqSource;
+use crate::utils::civil_from_days;
+use crate::ModesMessage;
+
+pub const SIGMF_VERSION: &str = "1.0.0";
+pub const SIGMF_DATA_EXTENSION: &str = "sigmf-data";
+pub const SIGMF_META_EXTENSION: &str = "sigmf-meta";
+
+/// Sample rate of every recording, the only one the demodulator supports
+pub const SIGMF_SAMPLE_RATE: f64 = 2_400_000.0;
+
+/// Contents of a `.sigmf-meta` file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigmfMeta {
+    pub global: SigmfGlobal,
+    pub captures: Vec<SigmfCapture>,
+    #[serde(default)]
+    pub annotations: Vec<SigmfAnnotation>,
+}
+
+/// The `global` object, describing the whole recording
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigmfGlobal {
+    #[serde(rename = "core:datatype")]
+    pub datatype: String,
+    #[serde(rename = "core:sample_rate", default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    #[serde(rename = "core:version")]
+    pub version: String,
+    /// Sdr and its settings, like the gain
+    #[serde(rename = "core:hw", default, skip_serializing_if = "Option::is_none")]
+    pub hw: Option<String>,
+    #[serde(rename = "core:recorder", default, skip_serializing_if = "Option::is_none")]
+    pub recorder: Option<String>,
+    #[serde(rename = "core:description", default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A `captures` segment, the tuning from `sample_start` on
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigmfCapture {
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    /// Centre frequency in Hz
+    #[serde(rename = "core:frequency", default, skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<f64>,
+    /// Time of the first sample, ISO 8601 in UTC
+    #[serde(rename = "core:datetime", default, skip_serializing_if = "Option::is_none")]
+    pub datetime: Option<String>,
+}
+
+/// An `annotations` entry, here the position of a decoded message
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SigmfAnnotation {
+    #[serde(rename = "core:sample_start")]
+    pub sample_start: u64,
+    #[serde(rename = "core:sample_count", default, skip_serializing_if = "Option::is_none")]
+    pub sample_count: Option<u64>,
+    #[serde(rename = "core:label", default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(rename = "core:comment", default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+impl SigmfMeta {
+    /// Metadata for a new 2.4MHz recording tuned to `frequency`, starting at `start`
+    #[must_use]
+    pub fn new(frequency: f64, hw: Option<String>, start: SystemTime) -> Self {
+        Self {
+            global: SigmfGlobal {
+                datatype: sigmf_datatype(SampleFormat::Cs16).to_string(),
+                sample_rate: Some(SIGMF_SAMPLE_RATE),
+                version: SIGMF_VERSION.to_string(),
+                hw,
+                recorder: Some(
+                    concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")).to_string(),
+                ),
+                description: None,
+            },
+            captures: vec![SigmfCapture {
+                sample_start: 0,
+                frequency: Some(frequency),
+                datetime: Some(sigmf_datetime(start)),
+            }],
+            annotations: vec![],
+        }
+    }
+
+    /// The [`SampleFormat`] of the data file
+    pub fn sample_format(&self) -> io::Result<SampleFormat> {
+        match self.global.datatype.as_str() {
+            "cu8" => Ok(SampleFormat::Cu8),
+            "ci8" => Ok(SampleFormat::Cs8),
+            "ci16_le" => Ok(SampleFormat::Cs16),
+            "cf32_le" => Ok(SampleFormat::Cf32),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported SigMF datatype {:?}", self.global.datatype),
+            )),
+        }
+    }
+}
+
+impl SigmfAnnotation {
+    /// Annotation covering the preamble and data of `msg`
+    ///
+    /// `first_sample_timestamp_12mhz` is the timestamp the demodulator gave the first sample of
+    /// the recording, see [`crate::demod_2400::Demodulator::next_sample_timestamp_12mhz`].
+    #[must_use]
+    pub fn for_message(msg: &ModesMessage, first_sample_timestamp_12mhz: u64) -> Self {
+        // the timestamp is taken after the preamble and the first 56 bits, 12 ticks per us
+        let preamble = msg.timestamp_12mhz.saturating_sub((8 + 56) * 12);
+        let sample_start = preamble.saturating_sub(first_sample_timestamp_12mhz) / 5;
+        // 8us of preamble and 1us per bit, at 2.4 samples per us
+        let us = 8 + 8 * msg.len as u64;
+        Self {
+            sample_start,
+            sample_count: Some((us * 12 + 4) / 5),
+            label: Some(msg.bytes().iter().map(|b| format!("{b:02x}")).collect()),
+            comment: Some(format!("rssi {:.1} dBFS", msg.rssi)),
+        }
+    }
+}
+
+/// SigMF name of a [`SampleFormat`], I is stored before Q in all of them
+#[must_use]
+pub fn sigmf_datatype(format: SampleFormat) -> &'static str {
+    match format {
+        SampleFormat::Cu8 => "cu8",
+        SampleFormat::Cs8 => "ci8",
+        SampleFormat::Cs16 => "ci16_le",
+        SampleFormat::Cf32 => "cf32_le",
+    }
+}
+
+/// Paths of the data and meta file of a recording
+///
+/// `path` can be either of them, or the common name without an extension.
+#[must_use]
+pub fn sigmf_paths<P: AsRef<Path>>(path: P) -> (PathBuf, PathBuf) {
+    let path = path.as_ref();
+    let base = match path.extension().and_then(|e| e.to_str()) {
+        Some(SIGMF_DATA_EXTENSION | SIGMF_META_EXTENSION) => path.with_extension(""),
+        _ => path.to_path_buf(),
+    };
+    // appended instead of `with_extension`, so names like `site_1.5` keep their dot
+    let with_extension = |extension: &str| {
+        let mut name = OsString::from(base.as_os_str());
+        name.push(".");
+        name.push(extension);
+        PathBuf::from(name)
+    };
+    (with_extension(SIGMF_DATA_EXTENSION), with_extension(SIGMF_META_EXTENSION))
+}
+
+/// Whether `path` names a SigMF data or meta file
+#[must_use]
+pub fn is_sigmf<P: AsRef<Path>>(path: P) -> bool {
+    matches!(
+        path.as_ref().extension().and_then(|e| e.to_str()),
+        Some(SIGMF_DATA_EXTENSION | SIGMF_META_EXTENSION)
+    )
+}
+
+/// Read the `.sigmf-meta` file of a recording
+pub fn read_meta<P: AsRef<Path>>(path: P) -> io::Result<SigmfMeta> {
+    let (_, meta_path) = sigmf_paths(path);
+    let meta = serde_json::from_reader(BufReader::new(File::open(meta_path)?))?;
+    Ok(meta)
+}
+
+/// Open a recording for playback, returning its metadata and a source for its samples
+///
+/// Fails for recordings the demodulator can't use, with another sample rate than 2.4MHz.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<(SigmfMeta, RawIqSource<BufReader<File>>)> {
+    let (data_path, _) = sigmf_paths(&path);
+    let meta = read_meta(&path)?;
+    let format = meta.sample_format()?;
+    match meta.global.sample_rate {
+        Some(rate) if rate == SIGMF_SAMPLE_RATE => (),
+        rate => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("sample rate {rate:?} is not 2.4MHz"),
+            ))
+        }
+    }
+    let source = RawIqSource::open(data_path, format)?;
+    Ok((meta, source))
+}
+
+/// Writes a recording as `ci16_le`, the format of the demodulator input
+///
+/// The meta file is written when the recording is created, so an interrupted recording can still
+/// be read, and again with the annotations by [`SigmfWriter::finish`].
+pub struct SigmfWriter {
+    data: BufWriter<File>,
+    meta_path: PathBuf,
+    meta: SigmfMeta,
+    samples_written: u64,
+}
+
+impl SigmfWriter {
+    /// Create `<base>.sigmf-data` and `<base>.sigmf-meta`, replacing existing files
+    pub fn create<P: AsRef<Path>>(base: P, mut meta: SigmfMeta) -> io::Result<Self> {
+        let (data_path, meta_path) = sigmf_paths(base);
+        meta.global.datatype = sigmf_datatype(SampleFormat::Cs16).to_string();
+        write_meta(&meta_path, &meta)?;
+        let data = BufWriter::new(File::create(data_path)?);
+        Ok(Self { data, meta_path, meta, samples_written: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[Complex<i16>]) -> io::Result<()> {
+        for sample in samples {
+            self.data.write_all(&sample.re.to_le_bytes())?;
+            self.data.write_all(&sample.im.to_le_bytes())?;
+        }
+        self.samples_written += samples.len() as u64;
+        Ok(())
+    }
+
+    /// Start a new capture segment at the next sample, for example after a gap in the recording
+    pub fn new_capture(&mut self, start: SystemTime) {
+        let frequency = self.meta.captures.last().and_then(|c| c.frequency);
+        self.meta.captures.push(SigmfCapture {
+            sample_start: self.samples_written,
+            frequency,
+            datetime: Some(sigmf_datetime(start)),
+        });
+    }
+
+    pub fn annotate(&mut self, annotation: SigmfAnnotation) {
+        self.meta.annotations.push(annotation);
+    }
+
+    #[must_use]
+    pub fn samples_written(&self) -> u64 {
+        self.samples_written
+    }
+
+    #[must_use]
+    pub fn meta(&self) -> &SigmfMeta {
+        &self.meta
+    }
+
+    /// Flush the samples and write the meta file with the annotations, sorted by position
+    pub fn finish(mut self) -> io::Result<SigmfMeta> {
+        self.data.flush()?;
+        self.meta.annotations.sort_by_key(|a| a.sample_start);
+        write_meta(&self.meta_path, &self.meta)?;
+        Ok(self.meta)
+    }
+}
+
+fn write_meta(path: &Path, meta: &SigmfMeta) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, meta)?;
+    file.write_all(b"\n")?;
+    file.flush()
+}
+
+// `YYYY-MM-DDTHH:MM:SS.mmmZ`
+fn sigmf_datetime(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
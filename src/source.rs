@@ -0,0 +1,147 @@
+// Sources of 2.4MHz IQ samples for the demodulator
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Stdin};
+use std::path::Path;
+
+use num_complex::Complex;
+
+use crate::convert::SampleFormat;
+use crate::rtl_tcp::RtlTcpClient;
+use crate::MODES_MAG_BUF_SAMPLES;
+
+/// Anything that produces a continuous stream of 2.4MHz complex samples
+pub trait SampleSource {
+    /// Read up to `buf.len()` samples, blocking until at least one is available
+    ///
+    /// `Ok(0)` means the source ended normally, like at the end of a file. Sources that should
+    /// never end, like an sdr, return an error instead.
+    fn read_samples(&mut self, buf: &mut [Complex<i16>]) -> io::Result<usize>;
+
+    /// Iterate over the samples in chunks of [`MODES_MAG_BUF_SAMPLES`]
+    fn into_chunks(self) -> SampleChunks<Self>
+    where
+        Self: Sized,
+    {
+        SampleChunks { source: self, done: false }
+    }
+}
+
+impl<S: SampleSource + ?Sized> SampleSource for Box<S> {
+    fn read_samples(&mut self, buf: &mut [Complex<i16>]) -> io::Result<usize> {
+        (**self).read_samples(buf)
+    }
+}
+
+impl SampleSource for RtlTcpClient {
+    fn read_samples(&mut self, buf: &mut [Complex<i16>]) -> io::Result<usize> {
+        match RtlTcpClient::read_samples(self, buf)? {
+            0 if !buf.is_empty() => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "rtl_tcp server closed the connection",
+            )),
+            len => Ok(len),
+        }
+    }
+}
+
+/// Raw interleaved IQ from a file, a pipe or any other reader, in any [`SampleFormat`]
+///
+/// The files written by [`crate::utils::save_test_data`] are [`SampleFormat::Cs16`] with I and
+/// Q swapped, which doesn't change the magnitude the demodulator works on.
+pub struct RawIqSource<R> {
+    reader: R,
+    format: SampleFormat,
+    raw: Vec<u8>,
+    // bytes at the start of `raw` that didn't make up a whole sample in the last read
+    pending: usize,
+}
+
+impl RawIqSource<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P, format: SampleFormat) -> io::Result<Self> {
+        Ok(Self::new(BufReader::new(File::open(path)?), format))
+    }
+}
+
+impl RawIqSource<Stdin> {
+    #[must_use]
+    pub fn stdin(format: SampleFormat) -> Self {
+        Self::new(io::stdin(), format)
+    }
+}
+
+impl<R: Read> RawIqSource<R> {
+    pub fn new(reader: R, format: SampleFormat) -> Self {
+        Self { reader, format, raw: vec![], pending: 0 }
+    }
+}
+
+impl<R: Read> SampleSource for RawIqSource<R> {
+    fn read_samples(&mut self, buf: &mut [Complex<i16>]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let sample_bytes = self.format.bytes_per_sample();
+        self.raw.resize(sample_bytes * buf.len(), 0);
+        let mut len = self.pending;
+        while len < sample_bytes {
+            match self.reader.read(&mut self.raw[len..]) {
+                // a trailing partial sample is dropped
+                Ok(0) => return Ok(0),
+                Ok(read) => len += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let used = len / sample_bytes * sample_bytes;
+        let samples = self.format.to_cs16(&self.raw[..used], buf);
+
+        self.raw.copy_within(used..len, 0);
+        self.pending = len - used;
+        Ok(samples)
+    }
+}
+
+/// Iterator over the samples of a [`SampleSource`] in chunks of [`MODES_MAG_BUF_SAMPLES`], from
+/// [`SampleSource::into_chunks`]
+///
+/// Every chunk is full except the last one, which holds whatever was left when the source ended.
+/// An error is returned once, and ends the iteration.
+pub struct SampleChunks<S> {
+    source: S,
+    done: bool,
+}
+
+impl<S: SampleSource> Iterator for SampleChunks<S> {
+    type Item = io::Result<Vec<Complex<i16>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut chunk = vec![Complex::new(0, 0); MODES_MAG_BUF_SAMPLES];
+        let mut len = 0;
+        while len < chunk.len() {
+            match self.source.read_samples(&mut chunk[len..]) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(read) => len += read,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        if len == 0 {
+            return None;
+        }
+        chunk.truncate(len);
+        Some(Ok(chunk))
+    }
+}
@@ -0,0 +1,195 @@
+// Synthetic Mode S signals: messages encoded with valid parity and PPM modulated into 2.4MHz
+// IQ, for testing the demodulator without captures
+
+use std::f64::consts::TAU;
+
+use num_complex::Complex;
+
+use crate::crc::modes_checksum;
+use crate::{MODES_LONG_MSG_BYTES, MODES_SHORT_MSG_BYTES};
+
+/// Samples per microsecond, the length of one bit
+pub const SAMPLES_PER_US: f64 = 2.4;
+
+// Pulses of the preamble, start times in us
+const PREAMBLE_PULSES: [f64; 4] = [0.0, 1.0, 3.5, 4.5];
+const PREAMBLE_US: f64 = 8.0;
+const PULSE_US: f64 = 0.5;
+
+/// DF17 extended squitter from `address` with capability 5 (airborne or on the ground)
+#[must_use]
+pub fn encode_df17(address: u32, me: &[u8; 7]) -> [u8; MODES_LONG_MSG_BYTES] {
+    let mut msg = [0; MODES_LONG_MSG_BYTES];
+    msg[0] = (17 << 3) | 5;
+    msg[1..4].copy_from_slice(&address.to_be_bytes()[1..]);
+    msg[4..11].copy_from_slice(me);
+    set_parity(&mut msg, 0);
+    msg
+}
+
+/// DF11 all call reply from `address`, with `iid` (0 for an acquisition squitter) in the
+/// low 7 bits of the parity
+#[must_use]
+pub fn encode_df11(address: u32, capability: u8, iid: u8) -> [u8; MODES_SHORT_MSG_BYTES] {
+    let mut msg = [0; MODES_SHORT_MSG_BYTES];
+    msg[0] = (11 << 3) | (capability & 0x7);
+    msg[1..4].copy_from_slice(&address.to_be_bytes()[1..]);
+    set_parity(&mut msg, u32::from(iid & 0x7f));
+    msg
+}
+
+/// DF4 surveillance altitude reply from `address`, which is overlaid on the parity
+///
+/// `ac13` is the 13 bit altitude code, see [`encode_ac13_field`].
+#[must_use]
+pub fn encode_df4(address: u32, flight_status: u8, ac13: u16) -> [u8; MODES_SHORT_MSG_BYTES] {
+    let mut msg = [0; MODES_SHORT_MSG_BYTES];
+    msg[0] = (4 << 3) | (flight_status & 0x7);
+    // DR and UM stay 0
+    msg[2] = ((ac13 >> 8) & 0x1f) as u8;
+    msg[3] = ac13 as u8;
+    set_parity(&mut msg, address & 0x00ff_ffff);
+    msg
+}
+
+/// The 13 bit altitude code for `feet` in 25ft steps, the inverse of
+/// [`crate::mode_s::decode_ac13_field`]
+///
+/// Returns `None` outside of -1000..=50175 ft.
+#[must_use]
+pub fn encode_ac13_field(feet: i32) -> Option<u16> {
+    if !(-1000..=50175).contains(&feet) {
+        return None;
+    }
+    let n = ((feet + 1000 + 12) / 25) as u16;
+    // insert M = 0 at bit 6 and Q = 1 at bit 4
+    Some(((n & 0x07e0) << 2) | ((n & 0x0010) << 1) | 0x0010 | (n & 0x000f))
+}
+
+// Fill the last 3 bytes with the CRC of the rest, xored with `overlay`
+fn set_parity(msg: &mut [u8], overlay: u32) {
+    let len = msg.len();
+    msg[len - 3..].fill(0);
+    let parity = modes_checksum(msg, len * 8) ^ overlay;
+    msg[len - 3..].copy_from_slice(&parity.to_be_bytes()[1..]);
+}
+
+/// One message on the air, as part of [`synthesize`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transmission {
+    /// Message bytes, sent most significant bit first
+    pub bytes: Vec<u8>,
+    /// Sample at which the preamble starts, fractions offset the pulses within the samples
+    pub start: f64,
+    /// Pulse amplitude relative to the full scale of `Complex<i16>`
+    pub amplitude: f64,
+    /// Carrier phase at the start of the preamble, in radians
+    pub phase: f64,
+    /// Carrier offset from the tuned frequency, in Hz
+    pub frequency_offset: f64,
+}
+
+impl Transmission {
+    /// `bytes` starting at sample `start`, at half of full scale with no phase or frequency
+    /// offset
+    #[must_use]
+    pub fn new(bytes: &[u8], start: f64) -> Self {
+        Self { bytes: bytes.to_vec(), start, amplitude: 0.5, phase: 0.0, frequency_offset: 0.0 }
+    }
+
+    /// Length in samples, preamble included
+    #[must_use]
+    pub fn sample_count(&self) -> f64 {
+        (PREAMBLE_US + 8.0 * self.bytes.len() as f64) * SAMPLES_PER_US
+    }
+
+    // Pulse start times in us since the start of the preamble
+    fn pulses(&self) -> impl Iterator<Item = f64> + '_ {
+        let bits = self.bytes.iter().flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1 == 1));
+        let data = bits.enumerate().map(|(i, bit)| {
+            // a one is a pulse in the first half of the bit, a zero in the second
+            PREAMBLE_US + i as f64 + if bit { 0.0 } else { PULSE_US }
+        });
+        PREAMBLE_PULSES.into_iter().chain(data)
+    }
+}
+
+/// Gaussian noise added by [`synthesize`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Noise {
+    /// RMS amplitude of the complex noise relative to the full scale of `Complex<i16>`
+    pub rms: f64,
+    /// Seed of the random generator, the same seed always gives the same noise
+    pub seed: u64,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self { rms: 0.0, seed: 1 }
+    }
+}
+
+/// `len` samples of 2.4MHz IQ holding `transmissions` plus `noise`
+///
+/// Each pulse is integrated over the samples it covers, like the low pass filter of an sdr would.
+/// Overlapping transmissions add up with their phases, and the result is rounded and clipped to
+/// `Complex<i16>`.
+#[must_use]
+pub fn synthesize(len: usize, transmissions: &[Transmission], noise: Noise) -> Vec<Complex<i16>> {
+    let mut signal = vec![Complex::new(0.0, 0.0); len];
+
+    for transmission in transmissions {
+        for pulse in transmission.pulses() {
+            let first = transmission.start + pulse * SAMPLES_PER_US;
+            let last = first + PULSE_US * SAMPLES_PER_US;
+            let from = first.floor().max(0.0) as usize;
+            let to = (last.ceil().max(0.0) as usize).min(len);
+            for (k, sample) in signal.iter_mut().enumerate().take(to).skip(from) {
+                // part of the sample period covered by the pulse
+                let covered = (last.min(k as f64 + 1.0) - first.max(k as f64)).max(0.0);
+                let t = (k as f64 + 0.5 - transmission.start) / (SAMPLES_PER_US * 1e6);
+                let carrier = transmission.phase + TAU * transmission.frequency_offset * t;
+                *sample += Complex::from_polar(transmission.amplitude * covered, carrier);
+            }
+        }
+    }
+
+    let mut random = Random::new(noise.seed);
+    let sigma = noise.rms / 2.0_f64.sqrt();
+    let scale = |x: f64| (x * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+    signal
+        .into_iter()
+        .map(|s| {
+            let s = if noise.rms > 0.0 { s + random.gaussian() * sigma } else { s };
+            Complex::new(scale(s.re), scale(s.im))
+        })
+        .collect()
+}
+
+// splitmix64, so that the noise is the same on every platform and version
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in (0, 1]
+    fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1_u64 << 53) as f64
+    }
+
+    // two independent standard normal values, Box-Muller
+    fn gaussian(&mut self) -> Complex<f64> {
+        let r = (-2.0 * self.uniform().ln()).sqrt();
+        Complex::from_polar(r, TAU * self.uniform())
+    }
+}
@@ -0,0 +1,446 @@
+// This module includes functionality translated from track.c
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::cpr::{decode_cpr_airborne, decode_cpr_relative, decode_cpr_surface};
+use crate::mode_s::{
+    Altitude, AltitudeSource, CprFrame, DownlinkFormat, Emergency, ExtendedSquitter, Squawk,
+    Velocity, VerticalRateSource, VerticalStatus,
+};
+use crate::ModesMessage;
+
+/// Time after which an aircraft is forgotten if nothing is heard from it, as in dump1090
+pub const DEFAULT_AIRCRAFT_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Maximum age difference of an even/odd pair for global CPR decoding
+const AIRBORNE_PAIR_MAX_AGE: Duration = Duration::from_secs(10);
+const SURFACE_PAIR_MAX_AGE: Duration = Duration::from_secs(50);
+
+/// Maximum range of the receiver in NM, as in dump1090
+pub const DEFAULT_MAX_RANGE: f64 = 300.0;
+
+// Maximum age of the last position when using it as a reference for local CPR decoding
+const RELATIVE_REFERENCE_MAX_AGE: Duration = Duration::from_secs(60);
+
+// Half the size of a CPR zone in NM, the farthest a local decode can be from its reference
+const AIRBORNE_HALF_ZONE: f64 = 180.0;
+const SURFACE_HALF_ZONE: f64 = 45.0;
+
+const EARTH_RADIUS_NM: f64 = 6371e3 / 1852.0;
+
+/// Configuration for a [`Tracker`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TrackerConfig {
+    /// Aircraft are removed by [`Tracker::expire`] after this long without a message
+    pub aircraft_timeout: Duration,
+    /// Receiver location (lat, lon) in degrees, used for local CPR decoding before a global
+    /// position is known and as the reference for surface positions
+    pub receiver: Option<(f64, f64)>,
+    /// Maximum range of the receiver in NM. A single frame decoded relative to the receiver
+    /// could be a zone away, so it is only trusted as far as no other zone is within this range:
+    /// up to 180 NM for airborne and 45 NM for surface positions, less when the range is larger.
+    pub max_range: f64,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> Self {
+        Self {
+            aircraft_timeout: DEFAULT_AIRCRAFT_TIMEOUT,
+            receiver: None,
+            max_range: DEFAULT_MAX_RANGE,
+        }
+    }
+}
+
+/// A value together with the time it was last updated
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tracked<T> {
+    pub value: T,
+    pub updated: Instant,
+}
+
+// Replace the tracked value, stamping it with `now`
+fn set<T>(field: &mut Option<Tracked<T>>, value: T, now: Instant) {
+    *field = Some(Tracked { value, updated: now });
+}
+
+/// State of one aircraft, merged from every message with its address
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aircraft {
+    /// 24 bit ICAO address
+    pub address: u32,
+    pub first_seen: Instant,
+    pub last_seen: Instant,
+    /// Number of messages received
+    pub messages: u64,
+    /// Signal level of the last message in dBFS
+    pub rssi: f64,
+    pub callsign: Option<Tracked<String>>,
+    /// Emitter category, see [`ExtendedSquitter::Identification`]
+    pub category: Option<Tracked<u8>>,
+    pub squawk: Option<Tracked<Squawk>>,
+    /// Barometric altitude
+    pub altitude: Option<Tracked<Altitude>>,
+    /// GNSS height, from GNSS airborne positions
+    pub gnss_height: Option<Tracked<Altitude>>,
+    /// GNSS minus barometric altitude in feet, from airborne velocity
+    pub gnss_baro_diff: Option<Tracked<i32>>,
+    /// Position (lat, lon) in degrees
+    pub position: Option<Tracked<(f64, f64)>>,
+    /// Ground speed in knots
+    pub ground_speed: Option<Tracked<f64>>,
+    /// Ground track in degrees
+    pub track: Option<Tracked<f64>>,
+    /// Indicated or true airspeed in knots
+    pub airspeed: Option<Tracked<u32>>,
+    /// Heading in degrees
+    pub heading: Option<Tracked<f64>>,
+    /// Vertical rate in ft/min
+    pub vertical_rate: Option<Tracked<(i32, VerticalRateSource)>>,
+    pub on_ground: Option<Tracked<bool>>,
+    pub emergency: Option<Tracked<Emergency>>,
+    /// Alert (squawk change) flag from the flight status or the surveillance status
+    pub alert: Option<Tracked<bool>>,
+    /// Special position identification flag
+    pub spi: Option<Tracked<bool>>,
+    even_cpr: Option<Tracked<(CprFrame, bool)>>,
+    odd_cpr: Option<Tracked<(CprFrame, bool)>>,
+}
+
+impl Aircraft {
+    fn new(address: u32, now: Instant) -> Self {
+        Self {
+            address,
+            first_seen: now,
+            last_seen: now,
+            messages: 0,
+            rssi: 0.0,
+            callsign: None,
+            category: None,
+            squawk: None,
+            altitude: None,
+            gnss_height: None,
+            gnss_baro_diff: None,
+            position: None,
+            ground_speed: None,
+            track: None,
+            airspeed: None,
+            heading: None,
+            vertical_rate: None,
+            on_ground: None,
+            emergency: None,
+            alert: None,
+            spi: None,
+            even_cpr: None,
+            odd_cpr: None,
+        }
+    }
+
+    fn update(&mut self, frame: &DownlinkFormat, config: &TrackerConfig, now: Instant) {
+        match frame {
+            DownlinkFormat::ShortAirAirSurveillance { vs, altitude, .. }
+            | DownlinkFormat::LongAirAirSurveillance { vs, altitude, .. } => {
+                set(&mut self.on_ground, *vs == VerticalStatus::OnGround, now);
+                if let Some(altitude) = altitude {
+                    set(&mut self.altitude, *altitude, now);
+                }
+            }
+            DownlinkFormat::SurveillanceAltitudeReply { fs, altitude, .. }
+            | DownlinkFormat::CommBAltitudeReply { fs, altitude, .. } => {
+                self.update_flight_status(fs.on_ground(), fs.alert(), fs.spi(), now);
+                if let Some(altitude) = altitude {
+                    set(&mut self.altitude, *altitude, now);
+                }
+            }
+            DownlinkFormat::SurveillanceIdentityReply { fs, squawk, .. }
+            | DownlinkFormat::CommBIdentityReply { fs, squawk, .. } => {
+                self.update_flight_status(fs.on_ground(), fs.alert(), fs.spi(), now);
+                set(&mut self.squawk, *squawk, now);
+            }
+            DownlinkFormat::AllCallReply { ca, .. } => {
+                if let Some(on_ground) = ca.on_ground() {
+                    set(&mut self.on_ground, on_ground, now);
+                }
+            }
+            DownlinkFormat::ExtendedSquitter { ca, me, .. } => {
+                if let Some(on_ground) = ca.on_ground() {
+                    set(&mut self.on_ground, on_ground, now);
+                }
+                self.update_extended_squitter(me, config, now);
+            }
+            DownlinkFormat::ExtendedSquitterNonTransponder { me: Some(me), .. } => {
+                self.update_extended_squitter(me, config, now);
+            }
+            _ => (),
+        }
+    }
+
+    fn update_flight_status(
+        &mut self,
+        on_ground: Option<bool>,
+        alert: bool,
+        spi: bool,
+        now: Instant,
+    ) {
+        if let Some(on_ground) = on_ground {
+            set(&mut self.on_ground, on_ground, now);
+        }
+        set(&mut self.alert, alert, now);
+        set(&mut self.spi, spi, now);
+    }
+
+    fn update_extended_squitter(
+        &mut self,
+        me: &ExtendedSquitter,
+        config: &TrackerConfig,
+        now: Instant,
+    ) {
+        match me {
+            ExtendedSquitter::NoPosition { altitude: Some(altitude) } => {
+                set(&mut self.altitude, *altitude, now);
+            }
+            ExtendedSquitter::Identification { category, callsign, .. } => {
+                set(&mut self.category, *category, now);
+                if !callsign.is_empty() {
+                    set(&mut self.callsign, callsign.clone(), now);
+                }
+            }
+            ExtendedSquitter::SurfacePosition { ground_speed, track, cpr, .. } => {
+                set(&mut self.on_ground, true, now);
+                if let Some(ground_speed) = ground_speed {
+                    set(&mut self.ground_speed, *ground_speed, now);
+                }
+                if let Some(track) = track {
+                    set(&mut self.track, *track, now);
+                }
+                self.update_position(cpr, true, config, now);
+            }
+            ExtendedSquitter::AirbornePosition { ss, altitude, source, cpr, .. } => {
+                self.update_flight_status(Some(false), ss.alert(), ss.spi(), now);
+                if let Some(altitude) = altitude {
+                    match source {
+                        AltitudeSource::Baro => set(&mut self.altitude, *altitude, now),
+                        AltitudeSource::Gnss => set(&mut self.gnss_height, *altitude, now),
+                    }
+                }
+                self.update_position(cpr, false, config, now);
+            }
+            ExtendedSquitter::AirborneVelocity(velocity) => {
+                match velocity.velocity {
+                    Some(Velocity::GroundSpeed { ground_speed, track, .. }) => {
+                        if let Some(ground_speed) = ground_speed {
+                            set(&mut self.ground_speed, ground_speed, now);
+                        }
+                        if let Some(track) = track {
+                            set(&mut self.track, track, now);
+                        }
+                    }
+                    Some(Velocity::Airspeed { airspeed, heading, .. }) => {
+                        if let Some(airspeed) = airspeed {
+                            set(&mut self.airspeed, airspeed, now);
+                        }
+                        if let Some(heading) = heading {
+                            set(&mut self.heading, heading, now);
+                        }
+                    }
+                    None => (),
+                }
+                if let Some(vertical_rate) = velocity.vertical_rate {
+                    set(
+                        &mut self.vertical_rate,
+                        (vertical_rate, velocity.vertical_rate_source),
+                        now,
+                    );
+                }
+                if let Some(diff) = velocity.gnss_baro_diff {
+                    set(&mut self.gnss_baro_diff, diff, now);
+                }
+            }
+            ExtendedSquitter::AircraftStatus { emergency, squawk, .. } => {
+                if let Some(emergency) = emergency {
+                    set(&mut self.emergency, *emergency, now);
+                }
+                if let Some(squawk) = squawk.filter(|squawk| squawk.0 != 0) {
+                    set(&mut self.squawk, squawk, now);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // track.c:updatePosition
+    // Try a global decode from a fresh even/odd pair first, then fall back to a local decode
+    // relative to the last known position or the receiver
+    fn update_position(
+        &mut self,
+        cpr: &CprFrame,
+        surface: bool,
+        config: &TrackerConfig,
+        now: Instant,
+    ) {
+        let frame = Some(Tracked { value: (*cpr, surface), updated: now });
+        if cpr.odd {
+            self.odd_cpr = frame;
+        } else {
+            self.even_cpr = frame;
+        }
+
+        let last_position = self
+            .position
+            .filter(|p| now.duration_since(p.updated) <= RELATIVE_REFERENCE_MAX_AGE)
+            .map(|p| p.value);
+        let reference = last_position.or(config.receiver);
+
+        if let Some(position) = self.global_position(surface, reference) {
+            set(&mut self.position, position, now);
+        } else if let Some((reflat, reflon)) = last_position {
+            if let Ok(position) = decode_cpr_relative(reflat, reflon, cpr, surface) {
+                set(&mut self.position, position, now);
+            }
+        } else if let Some(receiver) = config.receiver {
+            if let Some(position) = receiver_position(receiver, cpr, surface, config.max_range) {
+                set(&mut self.position, position, now);
+            }
+        }
+    }
+
+    fn global_position(&self, surface: bool, reference: Option<(f64, f64)>) -> Option<(f64, f64)> {
+        let (even, odd) = (self.even_cpr?, self.odd_cpr?);
+
+        // both frames need to be of the same kind and close enough together in time
+        if even.value.1 != surface || odd.value.1 != surface {
+            return None;
+        }
+        let max_age = if surface { SURFACE_PAIR_MAX_AGE } else { AIRBORNE_PAIR_MAX_AGE };
+        let (older, newer) = if odd.updated >= even.updated {
+            (even.updated, odd.updated)
+        } else {
+            (odd.updated, even.updated)
+        };
+        if newer.duration_since(older) > max_age {
+            return None;
+        }
+
+        let odd_is_latest = odd.updated >= even.updated;
+        let result = if surface {
+            let (reflat, reflon) = reference?;
+            decode_cpr_surface(reflat, reflon, &even.value.0, &odd.value.0, odd_is_latest)
+        } else {
+            decode_cpr_airborne(&even.value.0, &odd.value.0, odd_is_latest)
+        };
+        result.ok()
+    }
+}
+
+// track.c:doLocalCPR
+// Local decode relative to the receiver, rejected if it is farther than a position in the
+// next zone could be while still being in range
+fn receiver_position(
+    receiver: (f64, f64),
+    cpr: &CprFrame,
+    surface: bool,
+    max_range: f64,
+) -> Option<(f64, f64)> {
+    let half_zone = if surface { SURFACE_HALF_ZONE } else { AIRBORNE_HALF_ZONE };
+    let range_limit = if max_range <= half_zone {
+        max_range
+    } else if max_range < 2.0 * half_zone {
+        2.0 * half_zone - max_range
+    } else {
+        return None;
+    };
+
+    let position = decode_cpr_relative(receiver.0, receiver.1, cpr, surface).ok()?;
+    (greatcircle(receiver, position) <= range_limit).then_some(position)
+}
+
+// track.c:greatcircle
+// Distance in NM between two (lat, lon) positions in degrees
+fn greatcircle(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat0, lon0) = (from.0.to_radians(), from.1.to_radians());
+    let (lat1, lon1) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat1 - lat0) / 2.0).sin().powi(2)
+        + lat0.cos() * lat1.cos() * ((lon1 - lon0) / 2.0).sin().powi(2);
+    EARTH_RADIUS_NM * 2.0 * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Per-aircraft state built up from decoded messages, keyed by ICAO address
+#[derive(Debug)]
+pub struct Tracker {
+    config: TrackerConfig,
+    aircraft: HashMap<u32, Aircraft>,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self::new(TrackerConfig::default())
+    }
+}
+
+impl Tracker {
+    #[must_use]
+    pub fn new(config: TrackerConfig) -> Self {
+        Self { config, aircraft: HashMap::new() }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &TrackerConfig {
+        &self.config
+    }
+
+    /// Merge a message into the state of its aircraft, see [`Tracker::update_at`]
+    pub fn update(&mut self, msg: &ModesMessage) -> Option<&Aircraft> {
+        self.update_at(msg, Instant::now())
+    }
+
+    /// Merge a message received at `now` into the state of its aircraft
+    ///
+    /// Returns the updated aircraft, or `None` if the message doesn't decode or carries no
+    /// address.
+    pub fn update_at(&mut self, msg: &ModesMessage, now: Instant) -> Option<&Aircraft> {
+        let frame = msg.decode().ok()?;
+        let address = frame.address()?;
+
+        let config = self.config;
+        let aircraft = self.aircraft.entry(address).or_insert_with(|| Aircraft::new(address, now));
+        aircraft.last_seen = now;
+        aircraft.messages += 1;
+        aircraft.rssi = msg.rssi;
+        aircraft.update(&frame, &config, now);
+
+        Some(aircraft)
+    }
+
+    /// Remove aircraft that haven't been heard from in `aircraft_timeout`, see
+    /// [`Tracker::expire_at`]
+    pub fn expire(&mut self) {
+        self.expire_at(Instant::now());
+    }
+
+    /// Remove aircraft that haven't been heard from in `aircraft_timeout` as of `now`
+    pub fn expire_at(&mut self, now: Instant) {
+        let timeout = self.config.aircraft_timeout;
+        self.aircraft.retain(|_, a| now.saturating_duration_since(a.last_seen) <= timeout);
+    }
+
+    #[must_use]
+    pub fn get(&self, address: u32) -> Option<&Aircraft> {
+        self.aircraft.get(&address)
+    }
+
+    /// All tracked aircraft, in no particular order
+    pub fn aircraft(&self) -> impl Iterator<Item = &Aircraft> {
+        self.aircraft.values()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.aircraft.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.aircraft.is_empty()
+    }
+}
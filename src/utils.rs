@@ -1,8 +1,12 @@
-// crate
+// std
+use std::io;
+use std::path::Path;
 // third-party
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use num_complex::Complex;
-
+// crate
+use crate::convert::SampleFormat;
+use crate::source::{RawIqSource, SampleSource};
 use crate::MagnitudeBuffer;
 
 pub fn save_test_data(data: &[Complex<i16>]) {
@@ -19,6 +23,9 @@ pub fn save_test_data(data: &[Complex<i16>]) {
     }
 }
 
+/// Read the 0x20000 samples of a file from `save_test_data`, like the ones in `test_iq/`
+///
+/// Panics if the file is shorter, see `read_iq_file` for files of any length.
 #[must_use]
 pub fn read_test_data(filepath: &str) -> [Complex<i16>; 0x20000] {
     let mut file = std::fs::File::open(filepath).unwrap();
@@ -39,9 +46,33 @@ pub fn read_test_data(filepath: &str) -> [Complex<i16>; 0x20000] {
     r_buf
 }
 
+/// Stream a file from `save_test_data` of any length in chunks of `MODES_MAG_BUF_SAMPLES`
+///
+/// The samples are in the same order as from `read_test_data`. Only the last chunk can be
+/// shorter, and a read error ends the iteration.
+pub fn read_iq_file<P: AsRef<Path>>(
+    filepath: P,
+) -> io::Result<impl Iterator<Item = io::Result<Vec<Complex<i16>>>>> {
+    let chunks = RawIqSource::open(filepath, SampleFormat::Cs16)?.into_chunks();
+    Ok(chunks.map(|chunk| {
+        let mut chunk = chunk?;
+        // written Q first
+        for sample in &mut chunk {
+            *sample = Complex::new(sample.im, sample.re);
+        }
+        Ok(chunk)
+    }))
+}
+
 #[must_use]
 pub fn to_mag(data: &[Complex<i16>]) -> MagnitudeBuffer {
     let mut outbuf = MagnitudeBuffer::default();
+    to_mag_into(data, &mut outbuf);
+    outbuf
+}
+
+/// Append the magnitude of `data` to `outbuf`, which must have room for all of it
+pub fn to_mag_into(data: &[Complex<i16>], outbuf: &mut MagnitudeBuffer) {
     for b in data {
         // TODO: lookup table
         let i = b.im;
@@ -54,5 +85,18 @@ pub fn to_mag(data: &[Complex<i16>]) -> MagnitudeBuffer {
         let mag = f32::sqrt(mag_sqr);
         outbuf.push(mag.mul_add(f32::from(u16::MAX), 0.5) as u16);
     }
-    outbuf
+}
+
+// Days since 1970-01-01 to a (year, month, day) in the proleptic Gregorian calendar
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
 }
@@ -0,0 +1,94 @@
+// third-party
+use num_complex::Complex;
+// crate
+use libdump1090_rs::convert::SampleFormat;
+use libdump1090_rs::demod_2400::Demodulator;
+use libdump1090_rs::{utils, MagnitudeBuffer};
+
+const FILENAMES: [&str; 3] = [
+    "test_iq/test_1641427457780.iq",
+    "test_iq/test_1641428106243.iq",
+    "test_iq/test_1641428165033.iq",
+];
+
+// The samples in `format`, as another tool would have recorded them
+fn encode(samples: &[Complex<i16>], format: SampleFormat) -> Vec<u8> {
+    let to_u8 = |x: i16| ((i32::from(x) + 0x8000) >> 8) as u8;
+    let to_i8 = |x: i16| ((i32::from(x) + 0x80) >> 8).clamp(-128, 127) as i8 as u8;
+    samples
+        .iter()
+        .flat_map(|s| match format {
+            SampleFormat::Cu8 => vec![to_u8(s.re), to_u8(s.im)],
+            SampleFormat::Cs8 => vec![to_i8(s.re), to_i8(s.im)],
+            SampleFormat::Cs16 => [s.re.to_le_bytes(), s.im.to_le_bytes()].concat(),
+            SampleFormat::Cf32 => {
+                let scale = |x: i16| (f32::from(x) / 32768.0).to_le_bytes();
+                [scale(s.re), scale(s.im)].concat()
+            }
+        })
+        .collect()
+}
+
+// The new samples of a buffer. Only one buffer at a time lives on the stack this way, they are
+// too big for the test thread to hold several.
+fn magnitudes(mag: &MagnitudeBuffer) -> Box<[u16]> {
+    mag.data[326..326 + mag.length].into()
+}
+
+fn to_mag(format: SampleFormat, raw: &[u8]) -> Box<[u16]> {
+    magnitudes(&format.to_mag(raw))
+}
+
+fn cs16_to_mag(samples: &[Complex<i16>]) -> Box<[u16]> {
+    magnitudes(&utils::to_mag(samples))
+}
+
+#[test]
+fn test_same_magnitude() {
+    let samples = utils::read_test_data(FILENAMES[0]);
+    let expected = cs16_to_mag(&samples);
+
+    // lossless formats give exactly the same magnitudes
+    for format in [SampleFormat::Cs16, SampleFormat::Cf32] {
+        assert_eq!(to_mag(format, &encode(&samples, format)), expected, "{format}");
+    }
+
+    // 8 bit formats convert to 16 bit exactly, so both paths agree
+    for format in [SampleFormat::Cu8, SampleFormat::Cs8] {
+        let raw = encode(&samples, format);
+        let mut cs16 = vec![Complex::new(0, 0); samples.len()];
+        assert_eq!(format.to_cs16(&raw, &mut cs16), samples.len());
+        assert_eq!(to_mag(format, &raw), cs16_to_mag(&cs16), "{format}");
+    }
+}
+
+#[test]
+fn test_demodulate_formats() {
+    for filename in FILENAMES {
+        let samples = utils::read_test_data(filename);
+        let expected = Demodulator::default().demodulate2400(&utils::to_mag(&samples)).unwrap();
+        let expected: Vec<_> = expected.iter().map(|msg| msg.bytes().to_vec()).collect();
+
+        for format in [SampleFormat::Cu8, SampleFormat::Cs8, SampleFormat::Cs16, SampleFormat::Cf32]
+        {
+            let mag = format.to_mag(&encode(&samples, format));
+            let data = Demodulator::default().demodulate2400(&mag).unwrap();
+            let data: Vec<_> = data.iter().map(|msg| msg.bytes().to_vec()).collect();
+            assert_eq!(data, expected, "{filename} as {format}");
+        }
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!("cu8".parse(), Ok(SampleFormat::Cu8));
+    assert_eq!("CF32".parse(), Ok(SampleFormat::Cf32));
+    assert_eq!(SampleFormat::Cs16.to_string(), "cs16");
+    assert!("u8".parse::<SampleFormat>().is_err());
+
+    // trailing bytes that don't make up a sample are ignored
+    assert_eq!(SampleFormat::Cs16.to_mag(&[0; 7]).length, 1);
+    let mut out = [Complex::new(1, 1); 2];
+    assert_eq!(SampleFormat::Cu8.to_cs16(&[128, 127, 0], &mut out), 1);
+    assert_eq!(out[0], Complex::new(128, -128));
+}
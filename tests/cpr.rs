@@ -0,0 +1,62 @@
+// third-party
+use hexlit::hex;
+// crate
+use libdump1090_rs::cpr::{decode_cpr_airborne, decode_cpr_relative, decode_cpr_surface};
+use libdump1090_rs::mode_s::{decode, CprFrame, ExtendedSquitter};
+
+fn cpr(msg: &[u8]) -> CprFrame {
+    match decode(msg).unwrap().extended_squitter() {
+        Some(ExtendedSquitter::AirbornePosition { cpr, .. })
+        | Some(ExtendedSquitter::SurfacePosition { cpr, .. }) => *cpr,
+        other => panic!("not a position: {other:?}"),
+    }
+}
+
+fn assert_position((lat, lon): (f64, f64), expected: (f64, f64)) {
+    assert!(
+        (lat - expected.0).abs() < 1e-4 && (lon - expected.1).abs() < 1e-4,
+        "got ({lat}, {lon}), expected {expected:?}"
+    );
+}
+
+#[test]
+fn test_airborne_global() {
+    let even = cpr(&hex!("8d40621d58c382d690c8ac2863a7"));
+    let odd = cpr(&hex!("8d40621d58c386435cc412692ad6"));
+
+    assert_position(decode_cpr_airborne(&even, &odd, false).unwrap(), (52.25720, 3.91937));
+    assert_position(decode_cpr_airborne(&even, &odd, true).unwrap(), (52.26578, 3.93892));
+
+    // frames must be one of each
+    assert!(decode_cpr_airborne(&even, &even, false).is_err());
+    assert!(decode_cpr_airborne(&odd, &even, false).is_err());
+}
+
+#[test]
+fn test_airborne_relative() {
+    let even = cpr(&hex!("8d40621d58c382d690c8ac2863a7"));
+    assert_position(decode_cpr_relative(52.258, 3.918, &even, false).unwrap(), (52.25720, 3.91937));
+
+    // western hemisphere
+    let odd = CprFrame { odd: true, lat: 21667, lon: 47598 };
+    assert_position(decode_cpr_relative(37.0, -122.0, &odd, false).unwrap(), (37.6188, -122.3754));
+}
+
+#[test]
+fn test_surface() {
+    let even = CprFrame { odd: false, lat: 10381, lon: 12170 };
+    let odd = CprFrame { odd: true, lat: 86667, lon: 59320 };
+    assert_position(
+        decode_cpr_surface(37.0, -122.0, &even, &odd, false).unwrap(),
+        (37.6188, -122.3754),
+    );
+
+    // southern and eastern hemisphere, the 90 degree ambiguity resolved by the reference
+    let even = CprFrame { odd: false, lat: 48715, lon: 40159 };
+    let odd = CprFrame { odd: true, lat: 98147, lon: 82138 };
+    assert_position(
+        decode_cpr_surface(-33.5, 151.0, &even, &odd, true).unwrap(),
+        (-33.9425, 151.1750),
+    );
+    assert_position(decode_cpr_relative(-33.5, 151.0, &odd, true).unwrap(), (-33.9425, 151.1750));
+}
@@ -0,0 +1,180 @@
+// third-party
+use hexlit::hex;
+// crate
+use libdump1090_rs::mode_s::{
+    decode, decode_ac12_field, decode_ac13_field, AirspeedType, Altitude, AltitudeSource,
+    Capability, CprFrame, DownlinkFormat, Emergency, ExtendedSquitter, Squawk, Velocity,
+    VerticalRateSource,
+};
+
+#[test]
+fn test_identification() {
+    let frame = decode(&hex!("8d4840d6202cc371c32ce0576098")).unwrap();
+    assert_eq!(frame.df(), 17);
+    assert_eq!(frame.address(), Some(0x4840d6));
+    assert_eq!(
+        frame.extended_squitter(),
+        Some(&ExtendedSquitter::Identification {
+            tc: 4,
+            category: 0xa0,
+            callsign: "KLM1023".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_airborne_position() {
+    let frame = decode(&hex!("8d40621d58c382d690c8ac2863a7")).unwrap();
+    let Some(ExtendedSquitter::AirbornePosition { tc, altitude, source, cpr, .. }) =
+        frame.extended_squitter()
+    else {
+        panic!("not an airborne position: {frame:?}");
+    };
+    assert_eq!(*tc, 11);
+    assert_eq!(*altitude, Some(Altitude::feet(38000)));
+    assert_eq!(*source, AltitudeSource::Baro);
+    assert_eq!(*cpr, CprFrame { odd: false, lat: 93000, lon: 51372 });
+}
+
+#[test]
+fn test_all_call_reply() {
+    let frame = decode(&hex!("5dad92936265f5")).unwrap();
+    assert_eq!(
+        frame,
+        DownlinkFormat::AllCallReply { ca: Capability(5), address: 0xad9293, iid: 0 }
+    );
+    assert_eq!(frame.address(), Some(0xad9293));
+}
+
+#[test]
+fn test_surface_movement() {
+    let ground_speed = |movement: u8| {
+        // DF17 TC6, nothing but the movement field is set
+        let msg = [
+            0x8d,
+            0x48,
+            0x40,
+            0xd6,
+            (6 << 3) | (movement >> 4),
+            movement << 4,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        match decode(&msg).unwrap().extended_squitter() {
+            Some(ExtendedSquitter::SurfacePosition { ground_speed, .. }) => *ground_speed,
+            other => panic!("not a surface position: {other:?}"),
+        }
+    };
+
+    // the first code of every band
+    assert_eq!(ground_speed(0), None);
+    assert_eq!(ground_speed(1), Some(0.0));
+    assert_eq!(ground_speed(2), Some(0.125));
+    assert_eq!(ground_speed(9), Some(1.0));
+    assert_eq!(ground_speed(13), Some(2.0));
+    assert_eq!(ground_speed(39), Some(15.0));
+    assert_eq!(ground_speed(94), Some(70.0));
+    assert_eq!(ground_speed(109), Some(100.0));
+    assert_eq!(ground_speed(124), Some(175.0));
+    assert_eq!(ground_speed(125), None);
+
+    assert_eq!(ground_speed(12), Some(1.75));
+    assert_eq!(ground_speed(123), Some(170.0));
+}
+
+#[test]
+fn test_aircraft_status() {
+    let status = |subtype: u8| {
+        // emergency 1 and squawk 7700 in the layout of subtype 1
+        let msg = [0x8d, 0x48, 0x40, 0xd6, (28 << 3) | subtype, 0x2a, 0xaa, 0, 0, 0, 0, 0, 0, 0];
+        decode(&msg).unwrap().extended_squitter().cloned()
+    };
+
+    assert_eq!(
+        status(1),
+        Some(ExtendedSquitter::AircraftStatus {
+            subtype: 1,
+            emergency: Some(Emergency::General),
+            squawk: Some(Squawk(0x7700))
+        })
+    );
+    // a TCAS resolution advisory
+    assert_eq!(
+        status(2),
+        Some(ExtendedSquitter::AircraftStatus { subtype: 2, emergency: None, squawk: None })
+    );
+}
+
+#[test]
+fn test_short_message_length() {
+    assert!(decode(&hex!("8d4840d6202cc3")).is_err());
+    assert!(decode(&[]).is_err());
+}
+
+#[test]
+fn test_altitude() {
+    // 25 ft increments
+    assert_eq!(decode_ac12_field(0xc38), Some(Altitude::feet(38000)));
+    assert_eq!(decode_ac12_field(0x010), Some(Altitude::feet(-1000)));
+    assert_eq!(decode_ac13_field(0x1838), Some(Altitude::feet(38000)));
+
+    // Gillham coded, 100 ft increments
+    assert_eq!(decode_ac13_field(0x1080), Some(Altitude::feet(6300)));
+    assert_eq!(decode_ac13_field(0x1400), Some(Altitude::feet(-900)));
+    assert_eq!(decode_ac13_field(0x0d80), Some(Altitude::feet(22900)));
+    assert_eq!(decode_ac12_field(0x06c0), Some(Altitude::feet(22900)));
+
+    // not available, and illegal Gillham codes
+    assert_eq!(decode_ac13_field(0), None);
+    assert_eq!(decode_ac12_field(0), None);
+    assert_eq!(decode_ac13_field(0x0a00), None);
+
+    // metric
+    let metric = decode_ac13_field(0x0041).unwrap();
+    assert_eq!(metric, Altitude::meters(1));
+    assert_eq!(Altitude::meters(1000).in_feet(), 3281);
+}
+
+#[test]
+fn test_velocity_ground_speed() {
+    let frame = decode(&hex!("8d485020994409940838175b284f")).unwrap();
+    let Some(ExtendedSquitter::AirborneVelocity(velocity)) = frame.extended_squitter() else {
+        panic!("not a velocity: {frame:?}");
+    };
+    assert_eq!(velocity.subtype, 1);
+    assert!(!velocity.supersonic());
+    assert_eq!(velocity.vertical_rate, Some(-832));
+    assert_eq!(velocity.vertical_rate_source, VerticalRateSource::Gnss);
+    assert_eq!(velocity.gnss_baro_diff, Some(550));
+
+    let Some(Velocity::GroundSpeed { ew, ns, ground_speed, track }) = velocity.velocity else {
+        panic!("not a ground speed: {velocity:?}");
+    };
+    assert_eq!((ew, ns), (Some(-8), Some(-159)));
+    assert!((ground_speed.unwrap() - 159.20).abs() < 0.01);
+    assert!((track.unwrap() - 182.88).abs() < 0.01);
+}
+
+#[test]
+fn test_velocity_airspeed() {
+    let frame = decode(&hex!("8da05f219b06b6af189400cbc33f")).unwrap();
+    let Some(ExtendedSquitter::AirborneVelocity(velocity)) = frame.extended_squitter() else {
+        panic!("not a velocity: {frame:?}");
+    };
+    assert_eq!(velocity.subtype, 3);
+    assert_eq!(velocity.vertical_rate, Some(-2304));
+    assert_eq!(velocity.vertical_rate_source, VerticalRateSource::Baro);
+
+    let Some(Velocity::Airspeed { airspeed, airspeed_type, heading }) = velocity.velocity else {
+        panic!("not an airspeed: {velocity:?}");
+    };
+    assert_eq!(airspeed, Some(375));
+    assert_eq!(airspeed_type, AirspeedType::True);
+    assert!((heading.unwrap() - 243.98).abs() < 0.01);
+}
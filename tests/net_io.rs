@@ -0,0 +1,150 @@
+// std
+use std::time::{Duration, Instant, UNIX_EPOCH};
+// third-party
+use hexlit::hex;
+// crate
+use libdump1090_rs::net_io::avr::{decode_avr, encode_avr, encode_avr_mlat};
+use libdump1090_rs::net_io::beast::{beast_rssi, beast_signal_level, encode_beast, BeastDecoder};
+use libdump1090_rs::net_io::sbs::encode_sbs;
+use libdump1090_rs::tracker::Tracker;
+use libdump1090_rs::ModesMessage;
+
+fn message(bytes: &[u8], timestamp_12mhz: u64, rssi: f64) -> ModesMessage {
+    let mut data = [0; 14];
+    data[..bytes.len()].copy_from_slice(bytes);
+    ModesMessage {
+        data,
+        len: bytes.len(),
+        timestamp_12mhz,
+        rssi,
+        score: 0,
+        phase: 0,
+        corrected_bits: vec![],
+    }
+}
+
+#[test]
+fn test_beast_long() {
+    let msg = message(&hex!("8d4840d6202cc371c32ce0576098"), 0x0123_4567_89ab, 0.0);
+    assert_eq!(encode_beast(&msg), hex!("1a330123456789abff8d4840d6202cc371c32ce0576098").to_vec());
+}
+
+#[test]
+fn test_beast_escape() {
+    // -20dBFS is 0x1a, as are a timestamp byte and a message byte
+    let msg = message(&hex!("5dad9293621af5"), 0x1a_0000_0001, -20.0);
+    assert_eq!(encode_beast(&msg), hex!("1a32001a1a000000011a1a5dad9293621a1af5").to_vec());
+
+    // the timestamp wraps at 48 bits
+    let msg = message(&hex!("5dad92936265f5"), 0x1_0000_0000_0002, -20.0);
+    assert_eq!(encode_beast(&msg)[2..8], hex!("000000000002"));
+}
+
+#[test]
+fn test_beast_signal_level() {
+    assert_eq!(beast_signal_level(0.0), 255);
+    assert_eq!(beast_signal_level(-6.0), 128);
+    assert_eq!(beast_signal_level(-100.0), 1);
+    assert_eq!(beast_signal_level(f64::NEG_INFINITY), 0);
+}
+
+#[test]
+fn test_avr() {
+    let msg = message(&hex!("8d4840d6202cc371c32ce0576098"), 0x0123_4567_89ab, 0.0);
+    assert_eq!(encode_avr(&msg), "*8D4840D6202CC371C32CE0576098;\n");
+    assert_eq!(encode_avr_mlat(&msg), "@0123456789AB8D4840D6202CC371C32CE0576098;\n");
+
+    let msg = message(&hex!("5dad92936265f5"), 0x1_0000_0000_002a, 0.0);
+    assert_eq!(encode_avr(&msg), "*5DAD92936265F5;\n");
+    assert_eq!(encode_avr_mlat(&msg), "@00000000002A5DAD92936265F5;\n");
+}
+
+#[test]
+fn test_sbs() {
+    let mut tracker = Tracker::default();
+    let start = Instant::now();
+    let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+    let mut sbs = |bytes: &[u8], now| {
+        let msg = message(bytes, 0, -10.0);
+        let aircraft = tracker.update_at(&msg, now).unwrap();
+        encode_sbs(&msg, aircraft, time)
+    };
+    let header = |msg_type, address| {
+        format!("MSG,{msg_type},1,1,{address},1,2023/11/14,22:13:20.123,2023/11/14,22:13:20.123")
+    };
+
+    let line = sbs(&hex!("8d4840d6202cc371c32ce0576098"), start).unwrap();
+    assert_eq!(line, format!("{},KLM1023,,,,,,,,,,,\r\n", header(1, "4840D6")));
+
+    // the position is only sent once the pair completes
+    let line = sbs(&hex!("8d40621d58c382d690c8ac2863a7"), start).unwrap();
+    assert_eq!(line, format!("{},,38000,,,,,,,0,,0,0\r\n", header(3, "40621D")));
+    let line = sbs(&hex!("8d40621d58c386435cc412692ad6"), start + Duration::from_secs(1)).unwrap();
+    assert_eq!(line, format!("{},,38000,,,52.26578,3.93891,,,0,,0,0\r\n", header(3, "40621D")));
+
+    // alert and spi from the surveillance status
+    let line = sbs(&hex!("8d40621e5ac382d690c8ac2863a7"), start).unwrap();
+    assert_eq!(line, format!("{},,38000,,,,,,,-1,,0,0\r\n", header(3, "40621E")));
+    let line = sbs(&hex!("8d40621f5ec382d690c8ac2863a7"), start).unwrap();
+    assert_eq!(line, format!("{},,38000,,,,,,,0,,-1,0\r\n", header(3, "40621F")));
+
+    let line = sbs(&hex!("8d485020994409940838175b284f"), start).unwrap();
+    assert_eq!(line, format!("{},,,159,183,,,-832,,,,,\r\n", header(4, "485020")));
+
+    let line = sbs(&hex!("5dad92936265f5"), start).unwrap();
+    assert_eq!(line, format!("{},,,,,,,,,,,,0\r\n", header(8, "AD9293")));
+
+    // another aircraft's state is never mixed in
+    let msg = message(&hex!("8d4840d6202cc371c32ce0576098"), 0, -10.0);
+    assert_eq!(encode_sbs(&msg, tracker.get(0xad9293).unwrap(), time), None);
+}
+
+#[test]
+fn test_beast_decode() {
+    let short = message(&hex!("5dad9293621af5"), 0x1a_0000_0001, beast_rssi(0x1a));
+    let long = message(&hex!("8d4840d6202cc371c32ce0576098"), 0x0123_4567_89ab, 0.0);
+    let mode_ac = hex!("1a31000000000001ff0102");
+
+    let mut stream = vec![0xff, 0x1a, 0x00]; // garbage before the first frame
+    stream.extend(encode_beast(&short));
+    stream.extend(mode_ac);
+    stream.extend(encode_beast(&long));
+
+    // the result doesn't depend on how the stream is split up
+    for chunk_size in [1, 2, 7, stream.len()] {
+        let mut decoder = BeastDecoder::new();
+        let messages: Vec<_> = stream.chunks(chunk_size).flat_map(|c| decoder.decode(c)).collect();
+        assert_eq!(messages, vec![short.clone(), long.clone()]);
+    }
+
+    // a frame cut short by the next one is dropped
+    let mut stream = encode_beast(&long)[..10].to_vec();
+    stream.extend(encode_beast(&short));
+    assert_eq!(BeastDecoder::new().decode(&stream), vec![short]);
+}
+
+#[test]
+fn test_avr_decode() {
+    let msg = decode_avr("*8D4840D6202CC371C32CE0576098;\n").unwrap();
+    assert_eq!(msg.bytes(), hex!("8d4840d6202cc371c32ce0576098"));
+    assert_eq!(msg.timestamp_12mhz, 0);
+
+    let msg = decode_avr("@0123456789AB5dad92936265f5;").unwrap();
+    assert_eq!(msg.bytes(), hex!("5dad92936265f5"));
+    assert_eq!(msg.timestamp_12mhz, 0x0123_4567_89ab);
+
+    assert!(decode_avr("").is_err());
+    assert!(decode_avr("8D4840D6202CC371C32CE0576098;").is_err());
+    assert!(decode_avr("*8D4840D6202CC371C32CE05760;").is_err());
+    assert!(decode_avr("*8D4840D6202CC371C32CE057609;").is_err());
+    assert!(decode_avr("*8D4840D6202CC371C32CE05760XY;").is_err());
+    assert!(decode_avr("@0123;").is_err());
+}
+
+#[test]
+fn test_from_bytes() {
+    let msg = ModesMessage::from_bytes(&hex!("5dad92936265f5"), 42, -3.0).unwrap();
+    assert_eq!(msg, message(&hex!("5dad92936265f5"), 42, -3.0));
+    assert!(ModesMessage::from_bytes(&[], 0, 0.0).is_err());
+    assert!(ModesMessage::from_bytes(&hex!("8d4840d6202cc3"), 0, 0.0).is_err());
+}
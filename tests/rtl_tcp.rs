@@ -0,0 +1,93 @@
+// std
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+// third-party
+use num_complex::Complex;
+// crate
+use libdump1090_rs::demod_2400::Demodulator;
+use libdump1090_rs::rtl_tcp::{DongleInfo, RtlTcpClient, RtlTcpGain};
+use libdump1090_rs::utils;
+
+// A fake rtl_tcp server for one client: send the header, collect `commands` 5 byte commands,
+// then replay `samples` as unsigned 8 bit IQ and hang up
+fn fake_rtl_tcp(samples: Vec<Complex<i16>>, commands: usize) -> (u16, thread::JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"RTL0\x00\x00\x00\x05\x00\x00\x00\x1d").unwrap();
+
+        let mut received = vec![0; 5 * commands];
+        stream.read_exact(&mut received).unwrap();
+
+        let to_u8 = |x: i16| ((i32::from(x) + 0x8000) >> 8) as u8;
+        let raw: Vec<u8> = samples.iter().flat_map(|s| [to_u8(s.re), to_u8(s.im)]).collect();
+        // odd sized writes, so that samples are split between reads
+        for chunk in raw.chunks(4099) {
+            stream.write_all(chunk).unwrap();
+        }
+        received
+    });
+    (port, server)
+}
+
+#[test]
+fn test_rtl_tcp_commands() {
+    let (port, server) = fake_rtl_tcp(vec![], 5);
+
+    let mut client = RtlTcpClient::connect(("127.0.0.1", port)).unwrap();
+    assert_eq!(client.info(), DongleInfo { tuner_type: 5, gain_count: 29 });
+    client.set_frequency(1_090_000_000).unwrap();
+    client.set_sample_rate(2_400_000).unwrap();
+    client.set_gain(RtlTcpGain::Manual(49.6)).unwrap();
+    client.set_gain(RtlTcpGain::Auto).unwrap();
+
+    let mut buf = [Complex::new(0, 0); 16];
+    assert_eq!(client.read_samples(&mut buf).unwrap(), 0);
+    assert_eq!(
+        server.join().unwrap(),
+        [
+            [0x01, 0x40, 0xf8, 0x14, 0x80],
+            [0x02, 0x00, 0x24, 0x9f, 0x00],
+            [0x03, 0x00, 0x00, 0x00, 0x01],
+            [0x04, 0x00, 0x00, 0x01, 0xf0],
+            [0x03, 0x00, 0x00, 0x00, 0x00],
+        ]
+        .concat()
+    );
+}
+
+#[test]
+fn test_rtl_tcp_replay() {
+    for filename in [
+        "test_iq/test_1641427457780.iq",
+        "test_iq/test_1641428106243.iq",
+        "test_iq/test_1641428165033.iq",
+    ] {
+        let samples = utils::read_test_data(filename);
+        let expected = Demodulator::default().demodulate_samples(&samples).unwrap();
+        let (port, _server) = fake_rtl_tcp(samples.to_vec(), 0);
+
+        let mut client = RtlTcpClient::connect(("127.0.0.1", port)).unwrap();
+        let mut demodulator = Demodulator::default();
+        let mut buf = vec![Complex::new(0, 0); 10_000];
+        let mut received = vec![];
+        let mut count = 0;
+        loop {
+            let len = client.read_samples(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            count += len;
+            received.extend(demodulator.demodulate_samples(&buf[..len]).unwrap());
+        }
+
+        assert_eq!(count, samples.len());
+        let bytes = |messages: &[libdump1090_rs::ModesMessage]| -> Vec<Vec<u8>> {
+            messages.iter().map(|msg| msg.bytes().to_vec()).collect()
+        };
+        assert_eq!(bytes(&received), bytes(&expected), "{filename}");
+    }
+}
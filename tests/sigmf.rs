@@ -0,0 +1,129 @@
+// std
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+// third-party
+use num_complex::Complex;
+// crate
+use libdump1090_rs::demod_2400::Demodulator;
+use libdump1090_rs::sigmf::{self, SigmfAnnotation, SigmfMeta, SigmfWriter};
+use libdump1090_rs::source::SampleSource;
+use libdump1090_rs::{utils, ModesMessage};
+
+const FILENAME: &str = "test_iq/test_1641427457780.iq";
+
+// A fresh directory for the files of one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sigmf_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Record `samples` in a few writes while demodulating them, annotating every message
+fn record(base: &Path, samples: &[Complex<i16>]) -> (SigmfMeta, Vec<ModesMessage>) {
+    let start = UNIX_EPOCH + Duration::from_millis(1_641_427_457_780);
+    let meta = SigmfMeta::new(1_090_000_000.0, Some("rtlsdr, TUNER=49.6".to_string()), start);
+    let mut writer = SigmfWriter::create(base, meta).unwrap();
+
+    let mut demodulator = Demodulator::default();
+    let first_sample = demodulator.next_sample_timestamp_12mhz();
+    let mut messages = vec![];
+    for chunk in samples.chunks(50_000) {
+        writer.write_samples(chunk).unwrap();
+        for msg in demodulator.demodulate_samples(chunk).unwrap() {
+            writer.annotate(SigmfAnnotation::for_message(&msg, first_sample));
+            messages.push(msg);
+        }
+    }
+    assert_eq!(writer.samples_written(), samples.len() as u64);
+    (writer.finish().unwrap(), messages)
+}
+
+#[test]
+fn test_record_and_play() {
+    let dir = temp_dir("play");
+    let base = dir.join("site_1.5");
+    let samples = utils::read_test_data(FILENAME);
+    let (meta, messages) = record(&base, &samples);
+    assert!(!messages.is_empty());
+
+    // either file or the common name opens the recording
+    for path in [dir.join("site_1.5.sigmf-meta"), dir.join("site_1.5.sigmf-data"), base] {
+        let (read_meta, mut source) = sigmf::open(&path).unwrap();
+        assert_eq!(read_meta, meta);
+
+        let mut demodulator = Demodulator::default();
+        let mut buf = vec![Complex::new(0, 0); 70_000];
+        let mut played = vec![];
+        loop {
+            let len = source.read_samples(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            played.extend(demodulator.demodulate_samples(&buf[..len]).unwrap());
+        }
+        assert_eq!(played, messages);
+    }
+
+    // one annotation per message, covering its preamble and bits
+    let mean = |data: &[Complex<i16>]| {
+        data.iter().map(|s| f64::from(s.re).hypot(f64::from(s.im))).sum::<f64>() / data.len() as f64
+    };
+    let background = mean(&samples);
+    assert_eq!(meta.annotations.len(), messages.len());
+    for annotation in &meta.annotations {
+        let msg = messages.iter().find(|m| {
+            Some(m.bytes().iter().map(|b| format!("{b:02x}")).collect()) == annotation.label
+                && annotation.sample_start == (m.timestamp_12mhz - (8 + 56) * 12 - 326 * 5) / 5
+        });
+        let msg = msg.unwrap();
+        let count = if msg.len == 14 { 288 } else { 154 };
+        assert_eq!(annotation.sample_count, Some(count));
+
+        let start = annotation.sample_start as usize;
+        assert!(mean(&samples[start..start + count as usize]) > 2.0 * background);
+    }
+    assert!(meta.annotations.windows(2).all(|w| w[0].sample_start <= w[1].sample_start));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_meta_json() {
+    let dir = temp_dir("json");
+    let base = dir.join("capture");
+    let samples = utils::read_test_data(FILENAME);
+    record(&base, &samples[..20_000]);
+
+    let json = std::fs::read_to_string(dir.join("capture.sigmf-meta")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["global"]["core:datatype"], "ci16_le");
+    assert_eq!(json["global"]["core:sample_rate"], 2_400_000.0);
+    assert_eq!(json["global"]["core:version"], "1.0.0");
+    assert_eq!(json["global"]["core:hw"], "rtlsdr, TUNER=49.6");
+    assert_eq!(json["captures"][0]["core:sample_start"], 0);
+    assert_eq!(json["captures"][0]["core:frequency"], 1_090_000_000.0);
+    assert_eq!(json["captures"][0]["core:datetime"], "2022-01-06T00:04:17.780Z");
+
+    // I before Q
+    let data = std::fs::read(dir.join("capture.sigmf-data")).unwrap();
+    assert_eq!(data.len(), 20_000 * 4);
+    assert_eq!(i16::from_le_bytes([data[0], data[1]]), samples[0].re);
+    assert_eq!(i16::from_le_bytes([data[2], data[3]]), samples[0].im);
+
+    // only 2.4MHz recordings can be demodulated
+    let mut meta: SigmfMeta = serde_json::from_value(json).unwrap();
+    meta.global.sample_rate = Some(2_000_000.0);
+    std::fs::write(dir.join("capture.sigmf-meta"), serde_json::to_string(&meta).unwrap()).unwrap();
+    assert!(sigmf::open(&base).is_err());
+
+    // recordings from other tools, in other formats
+    meta.global.sample_rate = Some(2_400_000.0);
+    meta.global.datatype = "cu8".to_string();
+    std::fs::write(dir.join("capture.sigmf-meta"), serde_json::to_string(&meta).unwrap()).unwrap();
+    let (_, mut source) = sigmf::open(&base).unwrap();
+    let mut buf = vec![Complex::new(0, 0); 40_000];
+    assert_eq!(source.read_samples(&mut buf).unwrap(), 40_000);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,142 @@
+// std
+use std::io::{self, Read};
+// third-party
+use num_complex::Complex;
+// crate
+use libdump1090_rs::convert::SampleFormat;
+use libdump1090_rs::demod_2400::Demodulator;
+use libdump1090_rs::source::{RawIqSource, SampleSource};
+use libdump1090_rs::{utils, ModesMessage, MODES_MAG_BUF_SAMPLES};
+
+const FILENAME: &str = "test_iq/test_1641428165033.iq";
+
+// A reader that never returns more than `chunk` bytes at a time, like a pipe
+struct Trickle<R> {
+    reader: R,
+    chunk: usize,
+}
+
+impl<R: Read> Read for Trickle<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.chunk);
+        self.reader.read(&mut buf[..len])
+    }
+}
+
+// Demodulate everything from `source`, the way the server does
+fn demodulate_all<S: SampleSource>(mut source: S, buf_len: usize) -> (usize, Vec<ModesMessage>) {
+    let mut demodulator = Demodulator::default();
+    let mut buf = vec![Complex::new(0, 0); buf_len];
+    let mut count = 0;
+    let mut messages = vec![];
+    loop {
+        let len = source.read_samples(&mut buf).unwrap();
+        if len == 0 {
+            return (count, messages);
+        }
+        count += len;
+        messages.extend(demodulator.demodulate_samples(&buf[..len]).unwrap());
+    }
+}
+
+#[test]
+fn test_raw_iq_file() {
+    // the test files have I and Q swapped
+    let expected: Vec<_> =
+        utils::read_test_data(FILENAME).iter().map(|s| Complex::new(s.im, s.re)).collect();
+
+    let mut source = RawIqSource::open(FILENAME, SampleFormat::Cs16).unwrap();
+    let mut buf = vec![Complex::new(0, 0); expected.len() + 10];
+    let mut samples = vec![];
+    loop {
+        let len = source.read_samples(&mut buf).unwrap();
+        if len == 0 {
+            break;
+        }
+        samples.extend_from_slice(&buf[..len]);
+    }
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn test_partial_reads() {
+    let data = std::fs::read(FILENAME).unwrap();
+    let samples = utils::read_test_data(FILENAME);
+    let expected = Demodulator::default().demodulate_samples(&samples).unwrap();
+    assert!(!expected.is_empty());
+
+    // reads that split samples, through a boxed source like the server uses
+    for (chunk, buf_len) in [(4099, 10_007), (3, 5), (1 << 20, 1 << 20)] {
+        let source: Box<dyn SampleSource> =
+            Box::new(RawIqSource::new(Trickle { reader: &data[..], chunk }, SampleFormat::Cs16));
+        let (count, messages) = demodulate_all(source, buf_len);
+        assert_eq!(count, samples.len());
+        assert_eq!(messages, expected);
+    }
+
+    // a trailing partial sample is dropped
+    let (count, _) =
+        demodulate_all(RawIqSource::new(&data[..data.len() - 3], SampleFormat::Cs16), 1000);
+    assert_eq!(count, samples.len() - 1);
+}
+
+// A reader that fails once `data` is used up
+struct Failing<'a> {
+    data: &'a [u8],
+}
+
+impl Read for Failing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.data.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "gone"));
+        }
+        self.data.read(buf)
+    }
+}
+
+#[test]
+fn test_chunks() {
+    let data = std::fs::read(FILENAME).unwrap();
+    let samples = utils::read_test_data(FILENAME);
+
+    // a longer recording with a short last chunk, read through a pipe
+    let extra = 1000;
+    let mut long = data.repeat(3);
+    long.extend_from_slice(&data[..extra * 4]);
+    let source = RawIqSource::new(Trickle { reader: &long[..], chunk: 7777 }, SampleFormat::Cs16);
+    let chunks: Vec<_> = source.into_chunks().map(Result::unwrap).collect();
+    let lens: Vec<_> = chunks.iter().map(Vec::len).collect();
+    assert_eq!(samples.len(), MODES_MAG_BUF_SAMPLES);
+    assert_eq!(lens, [MODES_MAG_BUF_SAMPLES, MODES_MAG_BUF_SAMPLES, MODES_MAG_BUF_SAMPLES, extra]);
+
+    // messages are the same however the samples are chunked
+    let mut demodulator = Demodulator::default();
+    let mut messages = vec![];
+    for chunk in &chunks {
+        messages.extend(demodulator.demodulate_samples(chunk).unwrap());
+    }
+    let (_, expected) = demodulate_all(RawIqSource::new(&long[..], SampleFormat::Cs16), 10_007);
+    assert_eq!(messages, expected);
+
+    // an error ends the chunks
+    let source = RawIqSource::new(
+        Failing { data: &long[..(MODES_MAG_BUF_SAMPLES + 5) * 4] },
+        SampleFormat::Cs16,
+    );
+    let mut chunks = source.into_chunks();
+    assert_eq!(chunks.next().unwrap().unwrap().len(), MODES_MAG_BUF_SAMPLES);
+    assert_eq!(chunks.next().unwrap().unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    assert!(chunks.next().is_none());
+
+    // an empty source has no chunks
+    assert!(RawIqSource::new(&[][..], SampleFormat::Cs16).into_chunks().next().is_none());
+}
+
+#[test]
+fn test_read_iq_file() {
+    let chunks: Vec<_> = utils::read_iq_file(FILENAME).unwrap().map(Result::unwrap).collect();
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0], utils::read_test_data(FILENAME));
+
+    assert!(utils::read_iq_file("test_iq/missing.iq").is_err());
+}
@@ -0,0 +1,136 @@
+// third-party
+use hexlit::hex;
+// crate
+use libdump1090_rs::demod_2400::Demodulator;
+use libdump1090_rs::mode_s::{decode, decode_ac13_field, Altitude, DownlinkFormat};
+use libdump1090_rs::synth::{
+    encode_ac13_field, encode_df11, encode_df17, encode_df4, synthesize, Noise, Transmission,
+};
+use libdump1090_rs::ModesMessage;
+
+const ADDRESS: u32 = 0x4840d6;
+// KLM1023 identification
+const ME: [u8; 7] = hex!("202cc371c32ce0");
+
+fn demodulate(len: usize, transmissions: &[Transmission], noise: Noise) -> Vec<ModesMessage> {
+    let samples = synthesize(len, transmissions, noise);
+    Demodulator::default().demodulate_samples(&samples).unwrap()
+}
+
+fn bytes(messages: &[ModesMessage]) -> Vec<Vec<u8>> {
+    messages.iter().map(|m| m.bytes().to_vec()).collect()
+}
+
+#[test]
+fn test_encode() {
+    assert_eq!(encode_df17(ADDRESS, &ME), hex!("8d4840d6202cc371c32ce0576098"));
+    assert_eq!(encode_df11(0xad9293, 5, 0), hex!("5dad92936265f5"));
+
+    let Ok(DownlinkFormat::AllCallReply { address, iid, .. }) =
+        decode(&encode_df11(ADDRESS, 5, 42))
+    else {
+        panic!("not an all call reply");
+    };
+    assert_eq!((address, iid), (ADDRESS, 42));
+
+    let df4 = encode_df4(ADDRESS, 0, encode_ac13_field(38000).unwrap());
+    let Ok(DownlinkFormat::SurveillanceAltitudeReply { altitude, address, .. }) = decode(&df4)
+    else {
+        panic!("not a surveillance altitude reply");
+    };
+    assert_eq!(address, ADDRESS);
+    assert_eq!(altitude, Some(Altitude::feet(38000)));
+
+    for feet in [-1000, -25, 0, 1025, 12_350, 50_175] {
+        assert_eq!(decode_ac13_field(encode_ac13_field(feet).unwrap()), Some(Altitude::feet(feet)));
+    }
+    assert_eq!(encode_ac13_field(-1025), None);
+    assert_eq!(encode_ac13_field(50_200), None);
+}
+
+#[test]
+fn test_demodulate() {
+    let df17 = encode_df17(ADDRESS, &ME);
+    let df11 = encode_df11(ADDRESS, 5, 0);
+    // DF4 is only accepted from addresses already seen in a DF11 or DF17
+    let df4 = encode_df4(ADDRESS, 0, encode_ac13_field(38000).unwrap());
+
+    // every position within a sample, phase and amplitude
+    let mut transmissions = vec![];
+    for i in 0..30 {
+        let bytes: &[u8] = [&df17[..], &df11, &df4][i % 3];
+        let mut transmission =
+            Transmission::new(bytes, 1000.0 + 400.0 * i as f64 + i as f64 / 10.0);
+        transmission.phase = i as f64;
+        transmission.amplitude = 0.05 + 0.03 * i as f64;
+        transmissions.push(transmission);
+    }
+    let mut messages = demodulate(20_000, &transmissions, Noise::default());
+    // the demodulator doesn't skip past a decoded message, so some are found twice, like the
+    // repeated messages in `test.rs`
+    messages.dedup_by(|a, b| a.bytes() == b.bytes());
+    let expected: Vec<_> = transmissions.iter().map(|t| t.bytes.clone()).collect();
+    assert_eq!(bytes(&messages), expected);
+
+    // timestamps are after the preamble and 56 bits, 5 ticks per sample after the trailing
+    // samples of a fresh demodulator
+    for (msg, transmission) in messages.iter().zip(&transmissions) {
+        let expected = (326.0 + transmission.start) * 5.0 + f64::from((8 + 56) * 12);
+        assert!((msg.timestamp_12mhz as f64 - expected).abs() < 5.0, "{msg:?} {transmission:?}");
+        assert!(msg.corrected_bits.is_empty());
+    }
+
+    // louder messages have a higher rssi
+    assert!(messages.windows(2).all(|w| w[0].rssi < w[1].rssi));
+}
+
+#[test]
+fn test_frequency_offset() {
+    let df17 = encode_df17(ADDRESS, &ME);
+    for offset in [-200_000.0, 50_000.0, 300_000.0] {
+        let mut transmission = Transmission::new(&df17, 100.0);
+        transmission.frequency_offset = offset;
+        assert_eq!(bytes(&demodulate(1000, &[transmission], Noise::default())), [df17]);
+    }
+}
+
+#[test]
+fn test_overlap() {
+    let strong = encode_df17(ADDRESS, &ME);
+    let weak = encode_df17(0xabcdef, &ME);
+
+    // a weak message starting in the middle of a strong one is lost, the strong one is not
+    let mut first = Transmission::new(&strong, 100.0);
+    first.amplitude = 0.8;
+    let mut second = Transmission::new(&weak, 100.0 + first.sample_count() / 2.0);
+    second.amplitude = 0.02;
+    assert_eq!(
+        bytes(&demodulate(2000, &[first.clone(), second.clone()], Noise::default())),
+        [strong]
+    );
+
+    // without the overlap both are decoded
+    second.start += first.sample_count();
+    assert_eq!(bytes(&demodulate(2000, &[first, second], Noise::default())), [strong, weak]);
+}
+
+#[test]
+fn test_noise() {
+    let df17 = encode_df17(ADDRESS, &ME);
+    let transmissions: Vec<_> =
+        (0..50).map(|i| Transmission::new(&df17, 300.0 + 400.0 * f64::from(i))).collect();
+
+    // the same seed gives the same samples
+    let noise = Noise { rms: 0.05, seed: 7 };
+    assert_eq!(synthesize(1000, &[], noise), synthesize(1000, &[], noise));
+    assert_ne!(synthesize(1000, &[], noise), synthesize(1000, &[], Noise { seed: 8, ..noise }));
+
+    // fewer messages are decoded as the signal to noise ratio drops
+    let decoded: Vec<_> = [0.01, 0.2, 0.6, 2.0]
+        .into_iter()
+        .map(|rms| demodulate(21_000, &transmissions, Noise { rms, seed: 1 }).len())
+        .collect();
+    assert_eq!(decoded[0], 50);
+    assert!(decoded.windows(2).all(|w| w[0] >= w[1]), "{decoded:?}");
+    assert_eq!(decoded[3], 0);
+}
@@ -1,52 +1,154 @@
 // third-part
 use assert_hex::assert_eq_hex;
 use hexlit::hex;
+use num_complex::Complex;
 // crate
+use libdump1090_rs::demod_2400::{Demodulator, DemodulatorConfig};
+use libdump1090_rs::synth::{synthesize, Noise, Transmission};
 use libdump1090_rs::utils;
 
-fn routine(filename: &str, expected_data: &[[u8; 14]]) {
+fn routine(filename: &str, expected_data: &[&[u8]]) {
     let buf = utils::read_test_data(filename);
     let outbuf = utils::to_mag(&buf);
 
-    let data = libdump1090_rs::demod_2400::demodulate2400(&outbuf).unwrap();
+    let mut demodulator = Demodulator::default();
+    let data = demodulator.demodulate2400(&outbuf).unwrap();
+    let data: Vec<&[u8]> = data.iter().map(|msg| msg.bytes()).collect();
     assert_eq_hex!(expected_data, &*data);
 }
 
 #[test]
 fn test_01() {
     let filename = "test_iq/test_1641427457780.iq";
-    let expected_data = Vec::from([
-        hex!("8dad929358b9c6273f002169c02e"),
-        hex!("8daa2bc4f82100020049b8db9449"),
-        hex!("8daa2bc4f82100020049b8db9449"),
-        hex!("8da0aaa058bf163fcf860013e840"),
-    ]);
-    routine(filename, &expected_data);
+    let expected_data: &[&[u8]] = &[
+        &hex!("8dad929358b9c6273f002169c02e"),
+        &hex!("8daa2bc4f82100020049b8db9449"),
+        &hex!("8daa2bc4f82100020049b8db9449"),
+        &hex!("02e1971ce17c84"),
+        &hex!("8da0aaa058bf163fcf860013e840"),
+    ];
+    routine(filename, expected_data);
 }
 
 #[test]
 fn test_02() {
     let filename = "test_iq/test_1641428165033.iq";
-    let expected_data = Vec::from([
-        hex!("8da79de99909932f780c9e2f2f8f"),
-        hex!("8dac04d358a7820a86ac3709e689"),
-        hex!("8dac04d3ea4288669b5c082751d4"),
-        hex!("8da79de958bdf59c85104874adad"),
-        hex!("5dad92936265f525be017735997b"),
-    ]);
-    routine(filename, &expected_data);
+    let expected_data: &[&[u8]] = &[
+        &hex!("8da79de99909932f780c9e2f2f8f"),
+        &hex!("8dac04d358a7820a86ac3709e689"),
+        &hex!("8dac04d3ea4288669b5c082751d4"),
+        &hex!("8da79de958bdf59c85104874adad"),
+        &hex!("5dad92936265f5"),
+    ];
+    routine(filename, expected_data);
 }
 
 #[test]
 fn test_03() {
     let filename = "test_iq/test_1641428106243.iq";
-    let expected_data = Vec::from([
-        hex!("8da8aac8990c30b51808aa24e573"),
-        hex!("8dada6b9990cf61e4848af2a8656"),
-        hex!("8da4ba025885462008fa0a4a6eb2"),
-        hex!("8da4ba025885462008fa0a4a6eb2"),
-        hex!("8da4ba0299115f301074a72db6ff"),
-    ]);
-
-    routine(filename, &expected_data);
+    let expected_data: &[&[u8]] = &[
+        &hex!("8da8aac8990c30b51808aa24e573"),
+        &hex!("02e19838bff1d9"),
+        &hex!("8dada6b9990cf61e4848af2a8656"),
+        &hex!("8da4ba025885462008fa0a4a6eb2"),
+        &hex!("8da4ba025885462008fa0a4a6eb2"),
+        &hex!("8da70bd158b99203f07f5b00f2ac"),
+        &hex!("8da4ba0299115f301074a72db6ff"),
+    ];
+
+    routine(filename, expected_data);
+}
+
+#[test]
+fn test_metadata() {
+    let buf = utils::read_test_data("test_iq/test_1641428165033.iq");
+    let mut outbuf = utils::to_mag(&buf);
+    outbuf.first_sample_timestamp_12mhz = 1_000_000;
+
+    let mut demodulator = Demodulator::default();
+    let data = demodulator.demodulate2400(&outbuf).unwrap();
+
+    assert!(data.windows(2).all(|w| w[0].timestamp_12mhz < w[1].timestamp_12mhz));
+    for msg in &data {
+        assert!(msg.timestamp_12mhz > 1_000_000);
+        assert!(msg.timestamp_12mhz < 1_000_000 + 5 * outbuf.length as u64 + 1000);
+        assert!(msg.rssi < 0.0 && msg.rssi > -60.0, "{}", msg.rssi);
+        assert!(msg.score > 0);
+        assert!((4..=8).contains(&msg.phase));
+    }
+    assert_eq!(demodulator.stats().accepted, data.len() as u64);
+}
+
+#[test]
+fn test_streaming() {
+    let buf = utils::read_test_data("test_iq/test_1641427457780.iq");
+
+    let mut demodulator = Demodulator::default();
+    let expected = demodulator.demodulate2400(&utils::to_mag(&buf)).unwrap();
+
+    // odd sized reads, so that messages land on the boundaries
+    let mut demodulator = Demodulator::default();
+    let mut data = vec![];
+    for chunk in buf.chunks(10_007) {
+        data.extend(demodulator.demodulate_samples(chunk).unwrap());
+    }
+    assert_eq!(expected, data);
+
+    // split in the middle of the first message, 5 ticks per sample and 326 trailing samples
+    let start = (expected[0].timestamp_12mhz - (8 + 56) * 12) as usize / 5 - 326;
+    let (a, b) = buf.split_at(start + 100);
+    let mut demodulator = Demodulator::default();
+    let mut data = demodulator.demodulate_samples(a).unwrap();
+    assert!(data.is_empty());
+    data.extend(demodulator.demodulate_samples(b).unwrap());
+    assert_eq!(expected, data);
+}
+
+#[test]
+fn test_short_last_block() {
+    let buf = utils::read_test_data("test_iq/test_1641427457780.iq");
+    let mut demodulator = Demodulator::default();
+    assert_eq!(demodulator.demodulate_samples(&buf).unwrap().len(), 5);
+
+    // only the new samples and the trailing ones before them are scanned, not what is left of
+    // the full block before
+    let data = demodulator.demodulate_samples(&[Complex::new(0, 0); 1000]).unwrap();
+    assert!(data.is_empty());
+}
+
+#[test]
+fn test_crc_fix() {
+    let buf = utils::read_test_data("test_iq/test_1641428106243.iq");
+    let outbuf = utils::to_mag(&buf);
+
+    let mut demodulator = Demodulator::default();
+    let data = demodulator.demodulate2400(&outbuf).unwrap();
+    let corrected: Vec<_> = data.iter().filter(|msg| !msg.corrected_bits.is_empty()).collect();
+    assert_eq!(corrected.len(), 1);
+    assert_eq_hex!(corrected[0].bytes(), hex!("8da70bd158b99203f07f5b00f2ac"));
+    assert_eq!(corrected[0].corrected_bits.len(), 1);
+    assert_eq!(demodulator.stats().corrected, 1);
+
+    let config = DemodulatorConfig { nfix_crc: 0, ..DemodulatorConfig::default() };
+    let mut demodulator = Demodulator::new(config);
+    let data = demodulator.demodulate2400(&outbuf).unwrap();
+    assert_eq!(data.len(), 6);
+    assert!(data.iter().all(|msg| msg.corrected_bits.is_empty()));
+}
+
+#[test]
+fn test_zero_address() {
+    let df11 = hex!("5dad92936265f5");
+    let demodulate = |transmissions: &[Transmission]| {
+        let samples = synthesize(2000, transmissions, Noise::default());
+        let data = Demodulator::default().demodulate_samples(&samples).unwrap();
+        data.iter().map(|msg| msg.bytes().to_vec()).collect::<Vec<_>>()
+    };
+    assert_eq_hex!(demodulate(&[Transmission::new(&df11, 240.0)]), [df11.to_vec()]);
+
+    // An all zero DF0 has a parity of 0, the value of the empty slots in the ICAO filter. The
+    // reply 10us after it keeps the 112 demodulated bits from being all zero.
+    let zero = Transmission::new(&[0; 7], 240.0);
+    let next = Transmission::new(&df11, zero.start + zero.sample_count() + 24.0);
+    assert_eq_hex!(demodulate(&[zero, next]), [df11.to_vec()]);
 }
@@ -0,0 +1,134 @@
+// std
+use std::time::{Duration, Instant};
+// third-party
+use hexlit::hex;
+// crate
+use libdump1090_rs::mode_s::Altitude;
+use libdump1090_rs::tracker::{Tracker, TrackerConfig};
+use libdump1090_rs::ModesMessage;
+
+fn message(bytes: &[u8]) -> ModesMessage {
+    let mut data = [0; 14];
+    data[..bytes.len()].copy_from_slice(bytes);
+    ModesMessage {
+        data,
+        len: bytes.len(),
+        timestamp_12mhz: 0,
+        rssi: -10.0,
+        score: 0,
+        phase: 4,
+        corrected_bits: vec![],
+    }
+}
+
+#[test]
+fn test_merge_and_global_position() {
+    let mut tracker = Tracker::default();
+    let start = Instant::now();
+
+    tracker.update_at(&message(&hex!("8d40621d58c382d690c8ac2863a7")), start);
+    let aircraft = tracker.get(0x40621d).unwrap();
+    assert_eq!(aircraft.altitude.unwrap().value, Altitude::feet(38000));
+    // a single frame without a receiver location can't be placed
+    assert!(aircraft.position.is_none());
+
+    let now = start + Duration::from_secs(1);
+    tracker.update_at(&message(&hex!("8d40621d58c386435cc412692ad6")), now);
+    let aircraft = tracker.get(0x40621d).unwrap();
+    assert_eq!(aircraft.messages, 2);
+    assert!(!aircraft.on_ground.unwrap().value);
+    let position = aircraft.position.unwrap();
+    assert_eq!(position.updated, now);
+    assert!((position.value.0 - 52.26578).abs() < 1e-4);
+    assert!((position.value.1 - 3.93892).abs() < 1e-4);
+}
+
+#[test]
+fn test_stale_pair() {
+    let mut tracker = Tracker::default();
+    let start = Instant::now();
+
+    tracker.update_at(&message(&hex!("8d40621d58c382d690c8ac2863a7")), start);
+    tracker.update_at(
+        &message(&hex!("8d40621d58c386435cc412692ad6")),
+        start + Duration::from_secs(11),
+    );
+    assert!(tracker.get(0x40621d).unwrap().position.is_none());
+}
+
+#[test]
+fn test_receiver_reference() {
+    let mut tracker =
+        Tracker::new(TrackerConfig { receiver: Some((52.0, 4.0)), ..TrackerConfig::default() });
+
+    let aircraft =
+        tracker.update_at(&message(&hex!("8d40621d58c382d690c8ac2863a7")), Instant::now()).unwrap();
+    let (lat, lon) = aircraft.position.unwrap().value;
+    assert!((lat - 52.25720).abs() < 1e-4);
+    assert!((lon - 3.91937).abs() < 1e-4);
+}
+
+#[test]
+fn test_receiver_range() {
+    let msg = message(&hex!("8d40621d58c382d690c8ac2863a7"));
+
+    // 200 NM south the frame resolves to the zone on the other side of the receiver, which is
+    // farther than the 60 NM left between the 300 NM range and the next zone
+    let mut tracker =
+        Tracker::new(TrackerConfig { receiver: Some((48.9, 4.0)), ..TrackerConfig::default() });
+    assert_eq!(tracker.update_at(&msg, Instant::now()).unwrap().position, None);
+
+    // the right zone, but out of range
+    let mut tracker = Tracker::new(TrackerConfig {
+        receiver: Some((52.0, 4.0)),
+        max_range: 10.0,
+        ..TrackerConfig::default()
+    });
+    assert_eq!(tracker.update_at(&msg, Instant::now()).unwrap().position, None);
+}
+
+#[test]
+fn test_identification_and_velocity() {
+    let mut tracker = Tracker::default();
+    let now = Instant::now();
+
+    tracker.update_at(&message(&hex!("8d4840d6202cc371c32ce0576098")), now);
+    tracker.update_at(&message(&hex!("8d485020994409940838175b284f")), now);
+    assert_eq!(tracker.len(), 2);
+
+    let klm = tracker.get(0x4840d6).unwrap();
+    assert_eq!(klm.callsign.as_ref().unwrap().value, "KLM1023");
+    assert_eq!(klm.category.unwrap().value, 0xa0);
+
+    let other = tracker.get(0x485020).unwrap();
+    assert!((other.ground_speed.unwrap().value - 159.20).abs() < 0.01);
+    assert!((other.track.unwrap().value - 182.88).abs() < 0.01);
+    assert_eq!(other.vertical_rate.unwrap().value.0, -832);
+}
+
+#[test]
+fn test_expire() {
+    let mut tracker = Tracker::new(TrackerConfig {
+        aircraft_timeout: Duration::from_secs(30),
+        ..TrackerConfig::default()
+    });
+    let start = Instant::now();
+
+    tracker.update_at(&message(&hex!("5dad92936265f5")), start);
+    tracker.update_at(&message(&hex!("8d4840d6202cc371c32ce0576098")), start);
+    tracker.update_at(
+        &message(&hex!("8d4840d6202cc371c32ce0576098")),
+        start + Duration::from_secs(20),
+    );
+
+    tracker.expire_at(start + Duration::from_secs(30));
+    assert_eq!(tracker.len(), 2);
+
+    tracker.expire_at(start + Duration::from_secs(31));
+    assert_eq!(tracker.len(), 1);
+    assert!(tracker.get(0xad9293).is_none());
+    assert_eq!(tracker.get(0x4840d6).unwrap().messages, 2);
+
+    // garbage doesn't create aircraft
+    assert!(tracker.update_at(&message(&[]), start).is_none());
+}